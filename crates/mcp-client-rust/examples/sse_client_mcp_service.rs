@@ -109,6 +109,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         exponential_backoff: true,
         max_retries: Some(5),
         shutdown_timeout: Duration::from_secs(5),
+        ..Default::default()
     };
 
     // 连接到服务端 - 使用counter服务作为示例
@@ -1,19 +1,24 @@
 use std::sync::{
     Arc,
-    atomic::{AtomicU64, Ordering},
+    atomic::{AtomicBool, AtomicU64, Ordering},
 };
 
-use serde_json::json;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use tokio::{task::JoinHandle, time::timeout};
+use tracing::{debug, warn};
 
 use crate::{
     core::{
-        Tool,
+        Resource, ResourceTemplate, Tool,
+        prompt::Prompt,
         protocol::{
             message::{JsonRpcMessage, JsonRpcRequest},
             result::InitializeResult,
         },
+        utils::KeepaliveConfig,
     },
-    error::Result,
+    error::{Error, Result},
     transport::traits::{Connectable, NotifyChannel, RequestSender},
 };
 
@@ -21,7 +26,8 @@ pub struct McpClient {
     sender: Option<Arc<dyn RequestSender>>,
     notifier: Option<Arc<dyn NotifyChannel>>,
     connection: Option<Arc<dyn Connectable>>,
-    id_counter: AtomicU64,
+    id_counter: Arc<AtomicU64>,
+    alive: Arc<AtomicBool>,
 }
 
 impl McpClient {
@@ -30,7 +36,8 @@ impl McpClient {
             sender: None,
             notifier: None,
             connection: None,
-            id_counter: AtomicU64::new(1),
+            id_counter: Arc::new(AtomicU64::new(1)),
+            alive: Arc::new(AtomicBool::new(true)),
         }
     }
 
@@ -69,6 +76,60 @@ impl McpClient {
         self.send(message).await
     }
 
+    /// 检查服务端是否仍然存活（keepalive 超时后为 false）
+    pub fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::Relaxed)
+    }
+
+    /// 向服务端发送 ping，服务端应返回空结果
+    pub async fn ping(&self) -> Result<()> {
+        let sender = self
+            .sender
+            .clone()
+            .ok_or_else(|| Error::System("RequestSender not available".into()))?;
+        send_ping(sender.as_ref(), self.next_id()).await
+    }
+
+    /// 启动后台 keepalive 任务：定期 ping 服务端，超时则标记对端失效并关闭连接
+    pub fn spawn_keepalive(&self, config: KeepaliveConfig) -> Result<JoinHandle<()>> {
+        let sender = self
+            .sender
+            .clone()
+            .ok_or_else(|| Error::System("RequestSender not available".into()))?;
+        let connection = self.connection.clone();
+        let id_counter = self.id_counter.clone();
+        let alive = self.alive.clone();
+
+        Ok(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(config.interval);
+            // 第一次 tick 立即返回，跳过
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+                let id = id_counter.fetch_add(1, Ordering::Relaxed);
+                match timeout(config.timeout, send_ping(sender.as_ref(), id)).await {
+                    Ok(Ok(())) => debug!("Keepalive ping {} answered", id),
+                    Ok(Err(e)) => {
+                        warn!("Keepalive ping failed: {}", e);
+                        break;
+                    }
+                    Err(_) => {
+                        warn!("Keepalive ping timed out after {:?}", config.timeout);
+                        break;
+                    }
+                }
+            }
+
+            alive.store(false, Ordering::Relaxed);
+            if let Some(connection) = connection
+                && let Err(e) = connection.close().await
+            {
+                warn!("Failed to close connection after keepalive timeout: {}", e);
+            }
+        }))
+    }
+
     pub async fn initialize(&self) -> Result<InitializeResult> {
         let request = JsonRpcRequest::new(Some(self.next_id()), "initialize", None);
         let response = self.send_resquest(request).await?.try_into()?;
@@ -107,4 +168,61 @@ impl McpClient {
         let request = JsonRpcRequest::new(Some(self.next_id()), "prompts/get", Some(params));
         self.send_resquest(request).await
     }
+
+    /// 自动翻页，获取全部工具
+    pub async fn list_all_tools(&self) -> Result<Vec<Tool>> {
+        self.list_all("tools/list", "tools").await
+    }
+
+    /// 自动翻页，获取全部资源
+    pub async fn list_all_resources(&self) -> Result<Vec<Resource>> {
+        self.list_all("resources/list", "resources").await
+    }
+
+    /// 自动翻页，获取全部资源模板
+    pub async fn list_all_resource_templates(&self) -> Result<Vec<ResourceTemplate>> {
+        self.list_all("resources/templates/list", "resourceTemplates")
+            .await
+    }
+
+    /// 自动翻页，获取全部提示词
+    pub async fn list_all_prompts(&self) -> Result<Vec<Prompt>> {
+        self.list_all("prompts/list", "prompts").await
+    }
+
+    /// 按 nextCursor 依次请求每一页，直到服务端不再返回游标
+    async fn list_all<T: DeserializeOwned>(&self, method: &str, field: &str) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let params = cursor.take().map(|cursor| json!({ "cursor": cursor }));
+            let request = JsonRpcRequest::new(Some(self.next_id()), method, params);
+            let result = match self.send_resquest(request).await? {
+                JsonRpcMessage::Response(response) => response.result.unwrap_or_default(),
+                JsonRpcMessage::Error(e) => return Err(Error::Protocol(e.error.message)),
+                _ => return Err(Error::System("Unexpected response type".into())),
+            };
+
+            let page: Vec<T> =
+                serde_json::from_value(result.get(field).cloned().unwrap_or(json!([])))?;
+            items.extend(page);
+
+            match result.get("nextCursor").and_then(Value::as_str) {
+                Some(next) => cursor = Some(next.to_string()),
+                None => break,
+            }
+        }
+
+        Ok(items)
+    }
+}
+
+async fn send_ping(sender: &dyn RequestSender, id: u64) -> Result<()> {
+    let request = JsonRpcRequest::new(Some(id), "ping", None);
+    match sender.send(JsonRpcMessage::Request(request)).await? {
+        JsonRpcMessage::Response(_) => Ok(()),
+        JsonRpcMessage::Error(e) => Err(Error::Protocol(e.error.message)),
+        _ => Err(Error::System("Unexpected response type".into())),
+    }
 }
@@ -1,2 +1,4 @@
 pub mod http;
 pub mod sse;
+#[cfg(unix)]
+pub mod unix;
@@ -2,12 +2,15 @@ use std::{sync::Arc, time::Duration};
 
 use eventsource_client::{Client as SseClient, SSE};
 use futures::TryStreamExt;
-use mcp_core::protocol::message::{JsonRpcMessage, JsonRpcNotification};
+use mcp_core::{
+    protocol::message::{JsonRpcMessage, JsonRpcNotification, JsonRpcResponse},
+    utils::{QueueConfig, QueueStats, message_queue},
+};
 use serde_json;
 use service_utils_rs::utils::Request;
 use tokio::{
     spawn,
-    sync::{Notify, RwLock, mpsc},
+    sync::{Notify, RwLock},
     task::JoinHandle,
     time::timeout,
 };
@@ -35,6 +38,8 @@ pub struct SseConfig {
     pub max_retries: Option<usize>,
     /// 关闭超时时间
     pub shutdown_timeout: Duration,
+    /// 接收消息队列的容量与溢出策略
+    pub queue: QueueConfig,
 }
 
 impl Default for SseConfig {
@@ -46,6 +51,7 @@ impl Default for SseConfig {
             exponential_backoff: true,
             max_retries: None,
             shutdown_timeout: Duration::from_secs(5),
+            queue: QueueConfig::default(),
         }
     }
 }
@@ -63,6 +69,8 @@ pub struct SseTransport {
     pub message_sender: MessageSender,
     pub message_receiver: Option<MessageReceiver>,
     pub retry_count: Arc<RwLock<usize>>,
+    /// 最近收到的事件 ID，重连时通过 Last-Event-ID 续接原会话
+    pub last_event_id: Arc<RwLock<Option<String>>>,
 }
 
 impl SseTransport {
@@ -73,12 +81,8 @@ impl SseTransport {
 
     /// 使用配置创建 SSE Transport 数据
     pub fn with_config(url: impl Into<String>, config: SseConfig) -> Self {
-        let mut http_client = Request::new();
-        http_client
-            .set_default_headers(vec![("Content-Type", "application/json".to_string())])
-            .unwrap();
-
-        let (tx, rx) = mpsc::unbounded_channel();
+        let http_client = json_http_client();
+        let (tx, rx) = message_queue(config.queue);
 
         Self {
             url: url.into(),
@@ -92,6 +96,7 @@ impl SseTransport {
             message_sender: tx,
             message_receiver: Some(rx),
             retry_count: Arc::new(RwLock::new(0)),
+            last_event_id: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -164,6 +169,7 @@ impl SseTransport {
         let state = self.state.clone();
         let config = self.config.clone();
         let retry_count = self.retry_count.clone();
+        let last_event_id = self.last_event_id.clone();
 
         let handle = spawn(async move {
             handle_messages_loop(
@@ -174,6 +180,7 @@ impl SseTransport {
                 state,
                 config,
                 retry_count,
+                last_event_id,
                 shutdown,
             )
             .await;
@@ -243,13 +250,28 @@ impl SseTransport {
         Ok(())
     }
 
+    /// 获取接收消息队列的深度统计
+    pub fn queue_stats(&self) -> QueueStats {
+        self.message_sender.stats()
+    }
+
     /// 获取消息接收器
     pub fn take_message_receiver(&mut self) -> Option<MessageReceiver> {
         self.message_receiver.take()
     }
 }
 
+/// 创建默认发送 JSON 的 HTTP 客户端
+fn json_http_client() -> Request {
+    let mut http_client = Request::new();
+    http_client
+        .set_default_headers(vec![("Content-Type", "application/json".to_string())])
+        .unwrap();
+    http_client
+}
+
 // 后台消息处理循环
+#[allow(clippy::too_many_arguments)]
 async fn handle_messages_loop(
     sse_url: String,
     post_endpoint: Arc<RwLock<Option<String>>>,
@@ -258,6 +280,7 @@ async fn handle_messages_loop(
     state: Arc<RwLock<ConnectionState>>,
     config: SseConfig,
     retry_count: Arc<RwLock<usize>>,
+    last_event_id: Arc<RwLock<Option<String>>>,
     shutdown: CancellationToken,
 ) {
     let mut retries = 0;
@@ -281,6 +304,7 @@ async fn handle_messages_loop(
             post_endpoint.clone(),
             message_sender.clone(),
             endpoint_ready.clone(),
+            last_event_id.clone(),
             shutdown.clone(),
         )
         .await;
@@ -328,16 +352,21 @@ async fn handle_messages_once(
     post_endpoint: Arc<RwLock<Option<String>>>,
     message_sender: MessageSender,
     endpoint_ready: Arc<Notify>,
+    last_event_id: Arc<RwLock<Option<String>>>,
     shutdown: CancellationToken,
 ) -> Result<()> {
     debug!("Establishing SSE connection to {}", sse_url);
 
-    let client = eventsource_client::ClientBuilder::for_url(&sse_url)
-        .map_err(|e| {
-            error!("Failed to build SSE client: {}", e);
-            Error::System(format!("Failed to build SSE client: {}", e))
-        })?
-        .build();
+    let mut builder = eventsource_client::ClientBuilder::for_url(&sse_url).map_err(|e| {
+        error!("Failed to build SSE client: {}", e);
+        Error::System(format!("Failed to build SSE client: {}", e))
+    })?;
+    // 重连时带上 Last-Event-ID，服务端会补发断线期间的事件
+    if let Some(id) = last_event_id.read().await.clone() {
+        info!("Resuming SSE session from event {}", id);
+        builder = builder.last_event_id(id);
+    }
+    let client = builder.build();
 
     let mut stream = client.stream();
 
@@ -359,7 +388,14 @@ async fn handle_messages_once(
     info!("SSE connection established, processing messages");
 
     // 处理消息流
-    process_message_stream(stream, message_sender, shutdown).await?;
+    process_message_stream(
+        stream,
+        post_endpoint,
+        message_sender,
+        last_event_id,
+        shutdown,
+    )
+    .await?;
 
     Ok(())
 }
@@ -392,18 +428,33 @@ async fn wait_for_endpoint_event(
 
 async fn process_message_stream(
     mut stream: impl TryStreamExt<Ok = SSE, Error = eventsource_client::Error> + Unpin,
+    post_endpoint: Arc<RwLock<Option<String>>>,
     message_sender: MessageSender,
+    last_event_id: Arc<RwLock<Option<String>>>,
     shutdown: CancellationToken,
 ) -> Result<()> {
+    let http_client = json_http_client();
     loop {
         tokio::select! {
             maybe_event = stream.try_next() => {
                 match maybe_event {
                     Ok(Some(SSE::Event(e))) if e.event_type == "message" => {
-                        process_sse_message(
+                        let delivered = process_sse_message(
                             e.data,
+                            &http_client,
+                            &post_endpoint,
                             &message_sender,
                         ).await;
+                        if let Err(e) = delivered {
+                            // 队列已关闭（溢出断开或接收端已丢弃），重连也无意义
+                            error!("Message queue closed, stopping SSE transport: {}", e);
+                            shutdown.cancel();
+                            break;
+                        }
+                        // 只记录已交付的事件，未交付的在重连后由服务端补发
+                        if let Some(id) = e.id {
+                            *last_event_id.write().await = Some(id);
+                        }
                     }
                     Ok(Some(_)) => continue,
                     Ok(None) => {
@@ -426,15 +477,37 @@ async fn process_message_stream(
     Ok(())
 }
 
-async fn process_sse_message(data: String, message_sender: &MessageSender) {
+async fn process_sse_message(
+    data: String,
+    http_client: &Request,
+    post_endpoint: &RwLock<Option<String>>,
+    message_sender: &MessageSender,
+) -> Result<()> {
     match serde_json::from_str::<JsonRpcMessage>(&data) {
-        Ok(message) => {
-            if let Err(e) = message_sender.send(message) {
-                warn!("Failed to send message through channel: {}", e);
+        // 服务端的 keepalive ping 直接在 transport 层应答
+        Ok(JsonRpcMessage::Request(request)) if request.method == "ping" => {
+            let Some(post_url) = post_endpoint.read().await.clone() else {
+                warn!("Received ping before POST endpoint was discovered");
+                return Ok(());
+            };
+            let pong = JsonRpcMessage::Response(JsonRpcResponse::success(
+                request.id,
+                serde_json::json!({}),
+            ));
+            match serde_json::to_value(&pong) {
+                Ok(body) => {
+                    if let Err(e) = http_client.post(&post_url, &body, None).await {
+                        warn!("Failed to answer ping: {}", e);
+                    }
+                }
+                Err(e) => warn!("Failed to serialize ping response: {}", e),
             }
         }
+        // 队列满时按策略阻塞、丢弃最旧的通知或断开
+        Ok(message) => return message_sender.send(message).await,
         Err(err) => {
             warn!("Failed to parse SSE message: {}", err);
         }
     }
+    Ok(())
 }
@@ -0,0 +1,221 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex as StdMutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use mcp_core::protocol::message::{JsonRpcMessage, JsonRpcResponse};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{
+        UnixStream,
+        unix::{OwnedReadHalf, OwnedWriteHalf},
+    },
+    sync::{Mutex, RwLock, oneshot},
+    task::JoinHandle,
+    time::timeout,
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    error::{Error, Result},
+    transport::{
+        traits::{Connectable, NotifyChannel, RequestSender},
+        types::MessageHandler,
+    },
+};
+
+type Pending = Arc<StdMutex<HashMap<u64, oneshot::Sender<JsonRpcMessage>>>>;
+
+/// Unix 域套接字 Transport，每行一条 JSON-RPC 消息
+pub struct UnixSocketTransport {
+    path: PathBuf,
+    request_timeout: Duration,
+    writer: Arc<Mutex<Option<OwnedWriteHalf>>>,
+    pending: Pending,
+    handler: Arc<RwLock<Option<MessageHandler>>>,
+    reader_task: StdMutex<Option<JoinHandle<()>>>,
+}
+
+impl UnixSocketTransport {
+    /// 创建连接到指定套接字路径的 Transport（调用 start 后才真正连接）
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            request_timeout: Duration::from_secs(30),
+            writer: Arc::new(Mutex::new(None)),
+            pending: Default::default(),
+            handler: Arc::new(RwLock::new(None)),
+            reader_task: StdMutex::new(None),
+        }
+    }
+
+    /// 设置等待响应的超时时间
+    pub fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+
+    /// 写入一行消息
+    async fn write(&self, msg: &JsonRpcMessage) -> Result<()> {
+        write_line(&self.writer, msg).await
+    }
+}
+
+async fn write_line(writer: &Mutex<Option<OwnedWriteHalf>>, msg: &JsonRpcMessage) -> Result<()> {
+    let mut line = serde_json::to_string(msg)?;
+    line.push('\n');
+
+    let mut guard = writer.lock().await;
+    let writer = guard.as_mut().ok_or(Error::NotConnected)?;
+    writer.write_all(line.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+#[async_trait]
+impl Connectable for UnixSocketTransport {
+    async fn start(&self) -> Result<()> {
+        let stream = UnixStream::connect(&self.path).await?;
+        let (reader, writer) = stream.into_split();
+        *self.writer.lock().await = Some(writer);
+        info!("Connected to Unix socket {}", self.path.display());
+
+        let task = tokio::spawn(read_loop(
+            reader,
+            self.writer.clone(),
+            self.pending.clone(),
+            self.handler.clone(),
+        ));
+        if let Some(old) = self.reader_task.lock().unwrap().replace(task) {
+            old.abort();
+        }
+        Ok(())
+    }
+
+    async fn close(&self) -> Result<()> {
+        if let Some(mut writer) = self.writer.lock().await.take() {
+            let _ = writer.shutdown().await;
+        }
+        if let Some(task) = self.reader_task.lock().unwrap().take() {
+            task.abort();
+        }
+        // 丢弃所有等待中的请求，调用方会收到 ChannelClosed
+        self.pending.lock().unwrap().clear();
+        info!("Unix socket transport closed");
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RequestSender for UnixSocketTransport {
+    async fn send(&self, msg: JsonRpcMessage) -> Result<JsonRpcMessage> {
+        let id = match &msg {
+            JsonRpcMessage::Request(request) => request.id,
+            _ => None,
+        }
+        .ok_or(Error::UnsupportedMessage)?;
+
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, tx);
+        if let Err(e) = self.write(&msg).await {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(e);
+        }
+
+        match timeout(self.request_timeout, rx).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err(Error::ChannelClosed),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                Err(Error::System(format!(
+                    "Timeout waiting for response to request {}",
+                    id
+                )))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl NotifyChannel for UnixSocketTransport {
+    async fn notify(&self, msg: JsonRpcMessage) -> Result<()> {
+        self.write(&msg).await
+    }
+
+    async fn set_message_handler(&self, handler: MessageHandler) -> Result<()> {
+        *self.handler.write().await = Some(handler);
+        Ok(())
+    }
+}
+
+// 后台读取循环：响应按 id 交给等待者，其余消息交给回调
+async fn read_loop(
+    reader: OwnedReadHalf,
+    writer: Arc<Mutex<Option<OwnedWriteHalf>>>,
+    pending: Pending,
+    handler: Arc<RwLock<Option<MessageHandler>>>,
+) {
+    let mut lines = BufReader::new(reader).lines();
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => {
+                info!("Unix socket closed by server");
+                break;
+            }
+            Err(e) => {
+                warn!("Unix socket read failed: {}", e);
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let message = match serde_json::from_str::<JsonRpcMessage>(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Failed to parse message from Unix socket: {}", e);
+                continue;
+            }
+        };
+
+        let response_id = match &message {
+            JsonRpcMessage::Response(response) => response.id,
+            JsonRpcMessage::Error(error) => error.id,
+            _ => None,
+        };
+        if let Some(id) = response_id {
+            match pending.lock().unwrap().remove(&id) {
+                Some(tx) => {
+                    let _ = tx.send(message);
+                }
+                None => debug!("Dropping response to unknown request {}", id),
+            }
+            continue;
+        }
+
+        match message {
+            // 服务端的 keepalive ping 直接在 transport 层应答
+            JsonRpcMessage::Request(request) if request.method == "ping" => {
+                let pong = JsonRpcMessage::Response(JsonRpcResponse::success(
+                    request.id,
+                    serde_json::json!({}),
+                ));
+                if let Err(e) = write_line(&writer, &pong).await {
+                    warn!("Failed to answer ping: {}", e);
+                }
+            }
+            message => match &*handler.read().await {
+                Some(handler) => handler(message),
+                None => debug!("No message handler set, dropping message"),
+            },
+        }
+    }
+
+    // 连接断开，唤醒所有等待中的请求
+    pending.lock().unwrap().clear();
+}
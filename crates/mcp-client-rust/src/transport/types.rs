@@ -1,14 +1,16 @@
-use mcp_core::protocol::message::JsonRpcMessage;
-use tokio::sync::mpsc;
+use mcp_core::{
+    protocol::message::JsonRpcMessage,
+    utils::{QueueReceiver, QueueSender},
+};
 
 /// MessageHandler 是一个线程安全的通知消息处理函数类型
 pub type MessageHandler = Box<dyn Fn(JsonRpcMessage) + Send + Sync + 'static>;
 
-/// 消息发送通道类型
-pub type MessageSender = mpsc::UnboundedSender<JsonRpcMessage>;
+/// 消息发送通道类型（有界队列，满时按 QueueConfig 中的策略处理）
+pub type MessageSender = QueueSender;
 
 /// 消息接收通道类型
-pub type MessageReceiver = mpsc::UnboundedReceiver<JsonRpcMessage>;
+pub type MessageReceiver = QueueReceiver;
 
 /// Transport 状态
 #[derive(Debug, Clone, PartialEq)]
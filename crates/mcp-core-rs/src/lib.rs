@@ -10,6 +10,6 @@ pub mod utils;
 pub use annotation::Annotation;
 pub use mcp_error as error;
 pub use protocol::result::InitializeResult;
-pub use resource::{MimeType, Resource, ResourceContents};
+pub use resource::{MimeType, Resource, ResourceContents, ResourceTemplate};
 pub use role::Role;
-pub use tool::{Tool, ToolCall};
+pub use tool::{Tool, ToolAnnotations, ToolCall};
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of values in a single completion result.
+pub const MAX_COMPLETION_VALUES: usize = 100;
+
+/// What a `completion/complete` request asks to complete an argument of.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum CompletionReference {
+    #[serde(rename = "ref/prompt")]
+    Prompt { name: String },
+    #[serde(rename = "ref/resource")]
+    Resource { uri: String },
+}
+
+/// The argument being completed and what the user has typed so far.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CompletionArgument {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Completion {
+    /// At most [`MAX_COMPLETION_VALUES`] suggestions.
+    pub values: Vec<String>,
+    /// The number of matches, if known, which may exceed the values returned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_more: Option<bool>,
+}
+
+impl Completion {
+    /// Builds a completion from all matches, truncating them to [`MAX_COMPLETION_VALUES`].
+    pub fn from_matches(mut values: Vec<String>) -> Self {
+        let total = values.len();
+        values.truncate(MAX_COMPLETION_VALUES);
+        Self {
+            has_more: Some(total > values.len()),
+            total: u32::try_from(total).ok(),
+            values,
+        }
+    }
+}
@@ -9,3 +9,7 @@ pub const INVALID_REQUEST: i32 = -32600;
 pub const METHOD_NOT_FOUND: i32 = -32601;
 pub const INVALID_PARAMS: i32 = -32602;
 pub const INTERNAL_ERROR: i32 = -32603;
+
+// MCP-specific error codes
+pub const RESOURCE_NOT_FOUND: i32 = -32002;
+pub const RATE_LIMITED: i32 = -32029;
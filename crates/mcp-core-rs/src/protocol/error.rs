@@ -1,5 +1,13 @@
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Value, json};
+
+use crate::{
+    error::Error,
+    protocol::constants::{
+        INTERNAL_ERROR, INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND, PARSE_ERROR,
+        RATE_LIMITED, RESOURCE_NOT_FOUND,
+    },
+};
 
 /// Error information for JSON-RPC error responses.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -16,3 +24,113 @@ pub struct ErrorData {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<Value>,
 }
+
+impl ErrorData {
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// Attaches structured `data` to the error.
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+}
+
+/// Central mapping from crate errors to the JSON-RPC error sent on the wire.
+impl From<&Error> for ErrorData {
+    fn from(e: &Error) -> Self {
+        match e {
+            // Transports report text they cannot decode as these. A `Json` error comes from a
+            // handler and means the params did not have the expected shape.
+            Error::Utf8(_) | Error::InvalidMessage(_) => ErrorData::new(PARSE_ERROR, e.to_string()),
+            Error::InvalidRequest { .. } | Error::Protocol(_) | Error::UnsupportedMessage => {
+                ErrorData::new(INVALID_REQUEST, e.to_string())
+            }
+            Error::MethodNotFound(_) => ErrorData::new(METHOD_NOT_FOUND, e.to_string()),
+            Error::InvalidParameters(_) | Error::Json(_) => {
+                ErrorData::new(INVALID_PARAMS, e.to_string())
+            }
+            Error::ResourceNotFound(uri) => {
+                ErrorData::new(RESOURCE_NOT_FOUND, e.to_string()).with_data(json!({ "uri": uri }))
+            }
+            Error::RateLimited { scope, retry_after } => {
+                ErrorData::new(RATE_LIMITED, e.to_string()).with_data(json!({
+                    "scope": scope,
+                    "retryAfterMs": retry_after.as_millis() as u64,
+                }))
+            }
+            Error::Rpc {
+                code,
+                message,
+                data,
+            } => ErrorData {
+                code: *code,
+                message: message.clone(),
+                data: data.clone(),
+            },
+            _ => ErrorData::new(INTERNAL_ERROR, e.to_string()),
+        }
+    }
+}
+
+impl From<Error> for ErrorData {
+    fn from(e: Error) -> Self {
+        ErrorData::from(&e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_standard_codes() {
+        let cases = [
+            (Error::MethodNotFound("foo".into()), METHOD_NOT_FOUND),
+            (Error::InvalidParameters("bad".into()), INVALID_PARAMS),
+            (Error::InvalidMessage("bad".into()), PARSE_ERROR),
+            (
+                serde_json::from_str::<u32>("\"x\"").unwrap_err().into(),
+                INVALID_PARAMS,
+            ),
+            (
+                crate::utils::parse_json_rpc_message("{").unwrap_err(),
+                PARSE_ERROR,
+            ),
+            (
+                Error::InvalidRequest {
+                    id: Some(1),
+                    message: "bad".into(),
+                },
+                INVALID_REQUEST,
+            ),
+            (Error::System("boom".into()), INTERNAL_ERROR),
+        ];
+        for (error, code) in cases {
+            assert_eq!(ErrorData::from(&error).code, code, "{error}");
+        }
+    }
+
+    #[test]
+    fn test_resource_not_found_carries_uri() {
+        let data = ErrorData::from(Error::ResourceNotFound("memo://x".into()));
+        assert_eq!(data.code, RESOURCE_NOT_FOUND);
+        assert_eq!(data.data, Some(json!({ "uri": "memo://x" })));
+    }
+
+    #[test]
+    fn test_custom_code_passes_through() {
+        let error = Error::rpc_with_data(-32050, "quota exceeded", json!({ "limit": 10 }));
+        let data = ErrorData::from(error);
+        assert_eq!(data.code, -32050);
+        assert_eq!(data.message, "quota exceeded");
+        assert_eq!(data.data, Some(json!({ "limit": 10 })));
+    }
+}
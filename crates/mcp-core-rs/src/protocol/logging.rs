@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+/// Severity of a log message, as in syslog (RFC 5424), from least to most severe.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum LoggingLevel {
+    Debug,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Critical,
+    Alert,
+    Emergency,
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::protocol::{constants::JSONRPC_VERSION_FIELD, error::ErrorData};
+use crate::protocol::{constants::JSONRPC_EXPECTED_VERSION, error::ErrorData};
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(untagged, try_from = "JsonRpcRaw")]
@@ -26,7 +26,7 @@ pub struct JsonRpcRequest {
 impl JsonRpcRequest {
     pub fn new(id: Option<u64>, method: impl Into<String>, params: Option<Value>) -> Self {
         Self {
-            jsonrpc: JSONRPC_VERSION_FIELD.to_string(),
+            jsonrpc: JSONRPC_EXPECTED_VERSION.to_string(),
             id,
             method: method.into(),
             params,
@@ -48,7 +48,7 @@ pub struct JsonRpcResponse {
 impl JsonRpcResponse {
     pub fn empty(id: Option<u64>) -> Self {
         Self {
-            jsonrpc: JSONRPC_VERSION_FIELD.to_string(),
+            jsonrpc: JSONRPC_EXPECTED_VERSION.to_string(),
             id,
             result: None,
             error: None,
@@ -57,7 +57,7 @@ impl JsonRpcResponse {
 
     pub fn error(id: Option<u64>, error: ErrorData) -> Self {
         Self {
-            jsonrpc: JSONRPC_VERSION_FIELD.to_string(),
+            jsonrpc: JSONRPC_EXPECTED_VERSION.to_string(),
             id,
             result: None,
             error: Some(error),
@@ -66,7 +66,7 @@ impl JsonRpcResponse {
 
     pub fn success(id: Option<u64>, result: Value) -> Self {
         Self {
-            jsonrpc: JSONRPC_VERSION_FIELD.to_string(),
+            jsonrpc: JSONRPC_EXPECTED_VERSION.to_string(),
             id,
             result: Some(result),
             error: None,
@@ -82,6 +82,16 @@ pub struct JsonRpcNotification {
     pub params: Option<Value>,
 }
 
+impl JsonRpcNotification {
+    pub fn new(method: impl Into<String>, params: Option<Value>) -> Self {
+        Self {
+            jsonrpc: JSONRPC_EXPECTED_VERSION.to_string(),
+            method: method.into(),
+            params,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct JsonRpcError {
     pub jsonrpc: String,
@@ -1,6 +1,8 @@
 pub mod capabilities;
+pub mod completion;
 pub mod constants;
 pub mod error;
+pub mod logging;
 pub mod message;
 pub mod result;
 
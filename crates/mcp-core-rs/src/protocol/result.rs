@@ -1,11 +1,11 @@
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    Resource, ResourceContents, Tool,
+    Resource, ResourceContents, ResourceTemplate, Tool,
     content::Content,
     error::Error,
     prompt::{Prompt, PromptMessage},
-    protocol::{capabilities::ServerCapabilities, message::JsonRpcMessage},
+    protocol::{capabilities::ServerCapabilities, completion::Completion, message::JsonRpcMessage},
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -49,6 +49,14 @@ pub struct ListResourcesResult {
     pub next_cursor: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ListResourceTemplatesResult {
+    pub resource_templates: Vec<ResourceTemplate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ReadResourceResult {
     pub contents: Vec<ResourceContents>,
@@ -71,8 +79,11 @@ pub struct CallToolResult {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct ListPromptsResult {
     pub prompts: Vec<Prompt>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -82,5 +93,10 @@ pub struct GetPromptResult {
     pub messages: Vec<PromptMessage>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CompleteResult {
+    pub completion: Completion,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EmptyResult {}
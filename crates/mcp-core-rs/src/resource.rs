@@ -33,6 +33,49 @@ pub struct Resource {
     pub annotation: Option<Annotation>,
 }
 
+/// Describes a family of resources addressable through an RFC 6570 URI template
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceTemplate {
+    /// URI template used to construct resource URIs (e.g., "s3://bucket/{key}")
+    pub uri_template: String,
+    /// Name of the template
+    pub name: String,
+    /// Optional description of the resources produced by the template
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// MIME type shared by all resources matching the template, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotation: Option<Annotation>,
+}
+
+impl ResourceTemplate {
+    /// Creates a new ResourceTemplate with the given URI template and name
+    pub fn new<S: Into<String>>(uri_template: S, name: S) -> Self {
+        Self {
+            uri_template: uri_template.into(),
+            name: name.into(),
+            description: None,
+            mime_type: None,
+            annotation: None,
+        }
+    }
+
+    /// Sets the description of the template
+    pub fn with_description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Sets the MIME type of the template
+    pub fn with_mime_type<S: Into<String>>(mut self, mime_type: S) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase", untagged)]
 pub enum ResourceContents {
@@ -18,6 +18,47 @@ pub struct Tool {
     pub description: String,
     /// A JSON Schema object defining the expected parameters for the tool
     pub input_schema: Value,
+    /// Hints about how the tool behaves
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+}
+
+/// Hints about a tool's behavior. Clients must not rely on them for security, since a server
+/// can claim anything.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    /// A human-readable title for the tool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The tool does not modify its environment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    /// The tool may perform destructive updates; meaningful only when not read-only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    /// Repeating a call with the same arguments has no further effect
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+    /// The tool interacts with external entities, such as the web
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
+}
+
+impl ToolAnnotations {
+    pub fn read_only() -> Self {
+        Self {
+            read_only_hint: Some(true),
+            ..Default::default()
+        }
+    }
+
+    pub fn idempotent() -> Self {
+        Self {
+            idempotent_hint: Some(true),
+            ..Default::default()
+        }
+    }
 }
 
 impl Tool {
@@ -31,8 +72,28 @@ impl Tool {
             name: name.into(),
             description: description.into(),
             input_schema,
+            annotations: None,
         }
     }
+
+    pub fn with_annotations(mut self, annotations: ToolAnnotations) -> Self {
+        self.annotations = Some(annotations);
+        self
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.annotations
+            .as_ref()
+            .and_then(|a| a.read_only_hint)
+            .unwrap_or(false)
+    }
+
+    pub fn is_idempotent(&self) -> bool {
+        self.annotations
+            .as_ref()
+            .and_then(|a| a.idempotent_hint)
+            .unwrap_or(false)
+    }
 }
 
 impl TryFrom<JsonRpcMessage> for Vec<Tool> {
@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+/// Settings for periodic `ping` requests used to detect a dead peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeepaliveConfig {
+    /// How often a ping is sent to the peer.
+    pub interval: Duration,
+    /// How long to wait for the ping response before the peer is considered dead.
+    pub timeout: Duration,
+}
+
+impl KeepaliveConfig {
+    pub fn new(interval: Duration, timeout: Duration) -> Self {
+        Self { interval, timeout }
+    }
+}
+
+impl Default for KeepaliveConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
@@ -1,5 +1,11 @@
 pub mod cleanup;
+pub mod keepalive;
 pub mod parse_message;
+pub mod queue;
 
 pub use cleanup::CleanupStream;
+pub use keepalive::KeepaliveConfig;
 pub use parse_message::parse_json_rpc_message;
+pub use queue::{
+    OverflowPolicy, QueueConfig, QueueReceiver, QueueSender, QueueStats, message_queue,
+};
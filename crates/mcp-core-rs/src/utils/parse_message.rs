@@ -7,24 +7,33 @@ use crate::{
 };
 
 /// Parses a JSON-RPC message from a string, validating structure and version.
+///
+/// Text that is not JSON yields [`Error::InvalidMessage`]. JSON that is not a valid JSON-RPC
+/// message yields [`Error::InvalidRequest`], carrying the request id whenever one could be
+/// recovered so the peer can correlate the error response.
 pub fn parse_json_rpc_message(line: &str) -> Result<JsonRpcMessage> {
-    let value: serde_json::Value = serde_json::from_str(line)?;
-    if !value.is_object() {
-        return Err(Error::InvalidMessage(
-            "Message must be a JSON object".into(),
-        ));
-    }
-    let obj = value.as_object().unwrap();
+    let value: serde_json::Value =
+        serde_json::from_str(line).map_err(|e| Error::InvalidMessage(e.to_string()))?;
+    let Some(obj) = value.as_object() else {
+        return Err(Error::InvalidRequest {
+            id: None,
+            message: "Message must be a JSON object".into(),
+        });
+    };
+    let id = obj.get("id").and_then(serde_json::Value::as_u64);
 
     match obj.get(JSONRPC_VERSION_FIELD) {
         Some(serde_json::Value::String(v)) if v == JSONRPC_EXPECTED_VERSION => {}
         _ => {
-            return Err(Error::InvalidMessage(
-                "Missing or invalid jsonrpc version".into(),
-            ));
+            return Err(Error::InvalidRequest {
+                id,
+                message: "Missing or invalid jsonrpc version".into(),
+            });
         }
     }
 
-    let msg = serde_json::from_value(value)?;
-    Ok(msg)
+    serde_json::from_value(value).map_err(|e| Error::InvalidRequest {
+        id,
+        message: e.to_string(),
+    })
 }
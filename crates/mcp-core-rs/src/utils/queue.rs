@@ -0,0 +1,301 @@
+//! A bounded message queue with a configurable overflow policy.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use mcp_error::{Error, Result};
+use tokio::sync::Notify;
+
+use crate::protocol::message::JsonRpcMessage;
+
+/// Messages buffered when nothing else is configured.
+pub const DEFAULT_QUEUE_CAPACITY: usize = 64;
+
+/// What a sender does when the queue is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Wait until the receiver makes room.
+    #[default]
+    Block,
+    /// Evict the oldest queued notification to make room. Requests and responses are never
+    /// evicted, so with none of those queued the sender waits as with [`OverflowPolicy::Block`].
+    DropOldest,
+    /// Close the queue. The sender gets [`Error::QueueFull`] and the receiver sees the end.
+    Disconnect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueConfig {
+    pub capacity: usize,
+    pub policy: OverflowPolicy,
+}
+
+impl QueueConfig {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            policy: OverflowPolicy::default(),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self::new(DEFAULT_QUEUE_CAPACITY)
+    }
+}
+
+/// A snapshot of a queue's depth and history, for metrics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    /// Messages waiting right now.
+    pub depth: usize,
+    pub capacity: usize,
+    /// The deepest the queue has been.
+    pub high_water_mark: usize,
+    /// Notifications evicted under [`OverflowPolicy::DropOldest`].
+    pub dropped: u64,
+}
+
+struct State {
+    queue: VecDeque<JsonRpcMessage>,
+    closed: bool,
+    senders: usize,
+    receiver_alive: bool,
+    high_water_mark: usize,
+    dropped: u64,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    config: QueueConfig,
+    readable: Notify,
+    writable: Notify,
+}
+
+impl Shared {
+    fn stats(&self) -> QueueStats {
+        let state = self.state.lock().unwrap();
+        QueueStats {
+            depth: state.queue.len(),
+            capacity: self.config.capacity,
+            high_water_mark: state.high_water_mark,
+            dropped: state.dropped,
+        }
+    }
+}
+
+/// Creates a bounded queue of JSON-RPC messages.
+pub fn message_queue(config: QueueConfig) -> (QueueSender, QueueReceiver) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            queue: VecDeque::with_capacity(config.capacity),
+            closed: false,
+            senders: 1,
+            receiver_alive: true,
+            high_water_mark: 0,
+            dropped: 0,
+        }),
+        config,
+        readable: Notify::new(),
+        writable: Notify::new(),
+    });
+    (
+        QueueSender {
+            shared: shared.clone(),
+        },
+        QueueReceiver { shared },
+    )
+}
+
+pub struct QueueSender {
+    shared: Arc<Shared>,
+}
+
+impl QueueSender {
+    /// Queues a message, applying the overflow policy if the queue is full.
+    pub async fn send(&self, msg: JsonRpcMessage) -> Result<()> {
+        let mut msg = Some(msg);
+        loop {
+            // Registered before the state is checked, so a wakeup in between is not lost.
+            let writable = self.shared.writable.notified();
+            tokio::pin!(writable);
+            writable.as_mut().enable();
+
+            if self.try_push(&mut msg)? {
+                self.shared.readable.notify_one();
+                return Ok(());
+            }
+            writable.await;
+        }
+    }
+
+    /// Pushes `msg` if there is or can be made room, returning whether it was queued.
+    fn try_push(&self, msg: &mut Option<JsonRpcMessage>) -> Result<bool> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.closed || !state.receiver_alive {
+            return Err(Error::ChannelClosed);
+        }
+
+        if state.queue.len() >= self.shared.config.capacity {
+            match self.shared.config.policy {
+                OverflowPolicy::Block => return Ok(false),
+                OverflowPolicy::DropOldest => {
+                    let oldest = state
+                        .queue
+                        .iter()
+                        .position(|m| matches!(m, JsonRpcMessage::Notification(_)));
+                    let Some(oldest) = oldest else {
+                        return Ok(false);
+                    };
+                    state.queue.remove(oldest);
+                    state.dropped += 1;
+                }
+                OverflowPolicy::Disconnect => {
+                    state.closed = true;
+                    state.queue.clear();
+                    drop(state);
+                    self.shared.readable.notify_waiters();
+                    self.shared.writable.notify_waiters();
+                    return Err(Error::QueueFull);
+                }
+            }
+        }
+
+        state.queue.extend(msg.take());
+        state.high_water_mark = state.high_water_mark.max(state.queue.len());
+        Ok(true)
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        self.shared.stats()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        let state = self.shared.state.lock().unwrap();
+        state.closed || !state.receiver_alive
+    }
+}
+
+impl Clone for QueueSender {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().senders += 1;
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for QueueSender {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.senders -= 1;
+        if state.senders == 0 {
+            drop(state);
+            self.shared.readable.notify_waiters();
+        }
+    }
+}
+
+pub struct QueueReceiver {
+    shared: Arc<Shared>,
+}
+
+impl QueueReceiver {
+    /// Waits for the next message. Returns `None` once every sender is gone and the queue is
+    /// empty, or as soon as the queue was closed by [`OverflowPolicy::Disconnect`].
+    pub async fn recv(&mut self) -> Option<JsonRpcMessage> {
+        loop {
+            let readable = self.shared.readable.notified();
+            tokio::pin!(readable);
+            readable.as_mut().enable();
+
+            {
+                let mut state = self.shared.state.lock().unwrap();
+                if let Some(msg) = state.queue.pop_front() {
+                    drop(state);
+                    self.shared.writable.notify_one();
+                    return Some(msg);
+                }
+                if state.closed || state.senders == 0 {
+                    return None;
+                }
+            }
+            readable.await;
+        }
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        self.shared.stats()
+    }
+}
+
+impl Drop for QueueReceiver {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().receiver_alive = false;
+        self.shared.writable.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::message::{JsonRpcNotification, JsonRpcRequest};
+
+    fn notification(method: &str) -> JsonRpcMessage {
+        JsonRpcMessage::Notification(JsonRpcNotification::new(method, None))
+    }
+
+    fn method(msg: JsonRpcMessage) -> String {
+        match msg {
+            JsonRpcMessage::Request(r) => r.method,
+            JsonRpcMessage::Notification(n) => n.method,
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_overflow_policies() {
+        let (tx, mut rx) =
+            message_queue(QueueConfig::new(2).with_policy(OverflowPolicy::DropOldest));
+        tx.send(JsonRpcMessage::Request(JsonRpcRequest::new(
+            Some(1),
+            "a",
+            None,
+        )))
+        .await
+        .unwrap();
+        tx.send(notification("b")).await.unwrap();
+        tx.send(notification("c")).await.unwrap();
+        assert_eq!(tx.stats().dropped, 1);
+        assert_eq!(method(rx.recv().await.unwrap()), "a");
+        assert_eq!(method(rx.recv().await.unwrap()), "c");
+
+        let (tx, mut rx) = message_queue(QueueConfig::new(1));
+        tx.send(notification("a")).await.unwrap();
+        let blocked = tokio::spawn(async move { tx.send(notification("b")).await });
+        tokio::task::yield_now().await;
+        assert!(!blocked.is_finished());
+        assert_eq!(method(rx.recv().await.unwrap()), "a");
+        blocked.await.unwrap().unwrap();
+        assert_eq!(method(rx.recv().await.unwrap()), "b");
+        assert!(rx.recv().await.is_none());
+
+        let (tx, mut rx) =
+            message_queue(QueueConfig::new(1).with_policy(OverflowPolicy::Disconnect));
+        tx.send(notification("a")).await.unwrap();
+        assert!(matches!(
+            tx.send(notification("b")).await,
+            Err(Error::QueueFull)
+        ));
+        assert!(tx.is_closed());
+        assert!(rx.recv().await.is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use std::io;
+use std::{io, time::Duration};
 
 use thiserror::Error as ThisError;
 
@@ -25,6 +25,24 @@ pub enum Error {
     #[error("invalid parameters: {0}")]
     InvalidParameters(String),
 
+    #[error("invalid request: {message}")]
+    InvalidRequest { id: Option<u64>, message: String },
+
+    #[error("method not found: {0}")]
+    MethodNotFound(String),
+
+    #[error("resource not found: {0}")]
+    ResourceNotFound(String),
+
+    /// An error carrying an explicit JSON-RPC code, for services that need codes beyond the
+    /// standard mapping.
+    #[error("{message}")]
+    Rpc {
+        code: i32,
+        message: String,
+        data: Option<serde_json::Value>,
+    },
+
     #[error("Invalid message format: {0}")]
     InvalidMessage(String),
 
@@ -34,6 +52,33 @@ pub enum Error {
     #[error("Channel closed")]
     ChannelClosed,
 
+    #[error("Queue is full")]
+    QueueFull,
+
+    #[error("Tool '{tool}' timed out after {timeout:?}")]
+    ToolTimeout { tool: String, timeout: Duration },
+
+    #[error("Request '{method}' to the peer timed out after {timeout:?}")]
+    RequestTimeout { method: String, timeout: Duration },
+
+    #[error(
+        "Tool '{tool}' is busy: {max_concurrency} calls are running and {max_queue} are waiting"
+    )]
+    ToolBusy {
+        tool: String,
+        max_concurrency: usize,
+        max_queue: usize,
+    },
+
+    #[error("Rate limit exceeded for {scope}, retry after {retry_after:?}")]
+    RateLimited {
+        scope: String,
+        retry_after: Duration,
+    },
+
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+
     #[error("Unsupported message type. JsonRpcMessage can only be Request or Notification.")]
     UnsupportedMessage,
 
@@ -56,6 +101,26 @@ pub enum Error {
     MinioError(#[from] minio::s3::error::Error),
 }
 
+impl Error {
+    /// Creates an error that is reported to the peer with the given JSON-RPC code.
+    pub fn rpc(code: i32, message: impl Into<String>) -> Self {
+        Error::Rpc {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// Creates an error with a JSON-RPC code and structured `data`.
+    pub fn rpc_with_data(code: i32, message: impl Into<String>, data: serde_json::Value) -> Self {
+        Error::Rpc {
+            code,
+            message: message.into(),
+            data: Some(data),
+        }
+    }
+}
+
 pub type Result<T> = core::result::Result<T, Error>;
 
 pub type BoxError = Box<dyn std::error::Error + Sync + Send>;
@@ -1,5 +1,7 @@
+pub mod metrics;
 pub mod server;
 pub mod service;
+pub mod shutdown;
 pub mod transport;
 
 pub use mcp_core as core;
@@ -0,0 +1,471 @@
+//! Server metrics in the Prometheus text exposition format.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicI64, Ordering},
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use tower::Layer;
+
+use crate::{
+    core::{
+        content::Content,
+        protocol::{constants::INTERNAL_ERROR, error::ErrorData, message::JsonRpcResponse},
+        utils::QueueStats,
+    },
+    error::{Error, Result},
+    service::{
+        layer::{BoxError, METHODS, McpRequest},
+        middleware::{ToolCall, ToolMiddleware},
+    },
+};
+
+/// Upper bounds, in seconds, of the tool latency histogram buckets when nothing else is
+/// configured.
+pub const DEFAULT_LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// The content type of [`Metrics::render`]'s output.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Stands in for method and tool names that do not exist, so that clients cannot grow the set of
+/// labels without bound.
+const UNKNOWN: &str = "unknown";
+
+type QueueSource = Box<dyn Fn() -> QueueStats + Send + Sync>;
+
+struct Histogram {
+    /// Observations per bucket, not cumulative. The last one counts those above every bound.
+    counts: Vec<u64>,
+    sum: f64,
+}
+
+#[derive(Default)]
+struct Counters {
+    requests: BTreeMap<String, u64>,
+    errors: BTreeMap<(String, i32), u64>,
+    tool_calls: BTreeMap<String, Histogram>,
+    tool_errors: BTreeMap<String, u64>,
+}
+
+/// Counts requests, errors, tool latencies, sessions and queue depths, and renders them for
+/// Prometheus to scrape.
+///
+/// Give clones of one `Metrics` to the servers of every session with
+/// [`Server::with_metrics`](crate::server::Server::with_metrics); clones share their values.
+#[derive(Clone)]
+pub struct Metrics {
+    buckets: Arc<[f64]>,
+    counters: Arc<Mutex<Counters>>,
+    active_sessions: Arc<AtomicI64>,
+    queues: Arc<Mutex<BTreeMap<String, QueueSource>>>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            buckets: DEFAULT_LATENCY_BUCKETS.into(),
+            counters: Default::default(),
+            active_sessions: Default::default(),
+            queues: Default::default(),
+        }
+    }
+
+    /// Sets the upper bounds, in seconds, of the tool latency histogram buckets.
+    pub fn with_latency_buckets(mut self, buckets: impl IntoIterator<Item = f64>) -> Self {
+        let mut buckets: Vec<f64> = buckets.into_iter().filter(|b| b.is_finite()).collect();
+        buckets.sort_by(f64::total_cmp);
+        buckets.dedup();
+        self.buckets = buckets.into();
+        self
+    }
+
+    /// Counts a request, under `unknown` if [`McpService`](crate::service::layer::McpService)
+    /// does not handle its method.
+    pub fn record_request(&self, method: &str, error_code: Option<i32>) {
+        let method = if METHODS.contains(&method) {
+            method
+        } else {
+            UNKNOWN
+        };
+        let mut counters = self.counters.lock().unwrap();
+        *counters.requests.entry(method.to_string()).or_default() += 1;
+        if let Some(code) = error_code {
+            *counters
+                .errors
+                .entry((method.to_string(), code))
+                .or_default() += 1;
+        }
+    }
+
+    pub fn record_tool_call(&self, tool: &str, duration: Duration, failed: bool) {
+        let mut counters = self.counters.lock().unwrap();
+        let histogram = counters
+            .tool_calls
+            .entry(tool.to_string())
+            .or_insert_with(|| Histogram {
+                counts: vec![0; self.buckets.len() + 1],
+                sum: 0.0,
+            });
+        let seconds = duration.as_secs_f64();
+        let bucket = self.buckets.partition_point(|bound| *bound < seconds);
+        histogram.counts[bucket] += 1;
+        histogram.sum += seconds;
+        if failed {
+            *counters.tool_errors.entry(tool.to_string()).or_default() += 1;
+        }
+    }
+
+    /// Counts a session as active until the returned guard is dropped.
+    pub fn track_session(&self) -> ActiveSession {
+        self.active_sessions.fetch_add(1, Ordering::Relaxed);
+        ActiveSession(self.active_sessions.clone())
+    }
+
+    /// Reports the depth of a queue under `name`. `stats` is called on every scrape.
+    pub fn register_queue(
+        &self,
+        name: impl Into<String>,
+        stats: impl Fn() -> QueueStats + Send + Sync + 'static,
+    ) {
+        self.queues
+            .lock()
+            .unwrap()
+            .insert(name.into(), Box::new(stats));
+    }
+
+    pub fn unregister_queue(&self, name: &str) {
+        self.queues.lock().unwrap().remove(name);
+    }
+
+    /// Every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        {
+            let counters = self.counters.lock().unwrap();
+
+            header(
+                &mut out,
+                "mcp_requests_total",
+                "counter",
+                "Requests received, by method.",
+            );
+            for (method, count) in &counters.requests {
+                let _ = writeln!(
+                    out,
+                    "mcp_requests_total{{method=\"{}\"}} {count}",
+                    escape(method)
+                );
+            }
+
+            header(
+                &mut out,
+                "mcp_request_errors_total",
+                "counter",
+                "Requests answered with a JSON-RPC error, by method and code.",
+            );
+            for ((method, code), count) in &counters.errors {
+                let _ = writeln!(
+                    out,
+                    "mcp_request_errors_total{{method=\"{}\",code=\"{code}\"}} {count}",
+                    escape(method)
+                );
+            }
+
+            header(
+                &mut out,
+                "mcp_tool_call_duration_seconds",
+                "histogram",
+                "Tool call latency, by tool.",
+            );
+            for (tool, histogram) in &counters.tool_calls {
+                let tool = escape(tool);
+                let mut cumulative = 0;
+                let bounds = self.buckets.iter().map(|b| b.to_string());
+                for (bound, count) in bounds.chain(["+Inf".to_string()]).zip(&histogram.counts) {
+                    cumulative += count;
+                    let _ = writeln!(
+                        out,
+                        "mcp_tool_call_duration_seconds_bucket{{tool=\"{tool}\",le=\"{bound}\"}} \
+                         {cumulative}"
+                    );
+                }
+                let _ = writeln!(
+                    out,
+                    "mcp_tool_call_duration_seconds_sum{{tool=\"{tool}\"}} {}",
+                    histogram.sum
+                );
+                let _ = writeln!(
+                    out,
+                    "mcp_tool_call_duration_seconds_count{{tool=\"{tool}\"}} {cumulative}"
+                );
+            }
+
+            header(
+                &mut out,
+                "mcp_tool_errors_total",
+                "counter",
+                "Tool calls that failed, by tool.",
+            );
+            for (tool, count) in &counters.tool_errors {
+                let _ = writeln!(
+                    out,
+                    "mcp_tool_errors_total{{tool=\"{}\"}} {count}",
+                    escape(tool)
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "mcp_active_sessions",
+            "gauge",
+            "Sessions being served.",
+        );
+        let _ = writeln!(
+            out,
+            "mcp_active_sessions {}",
+            self.active_sessions.load(Ordering::Relaxed)
+        );
+
+        let queues: Vec<(String, QueueStats)> = self
+            .queues
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, stats)| (escape(name), stats()))
+            .collect();
+        header(
+            &mut out,
+            "mcp_queue_depth",
+            "gauge",
+            "Messages waiting in a queue.",
+        );
+        for (name, stats) in &queues {
+            let _ = writeln!(out, "mcp_queue_depth{{queue=\"{name}\"}} {}", stats.depth);
+        }
+        header(
+            &mut out,
+            "mcp_queue_capacity",
+            "gauge",
+            "Messages a queue can hold.",
+        );
+        for (name, stats) in &queues {
+            let _ = writeln!(
+                out,
+                "mcp_queue_capacity{{queue=\"{name}\"}} {}",
+                stats.capacity
+            );
+        }
+        header(
+            &mut out,
+            "mcp_queue_dropped_total",
+            "counter",
+            "Messages a queue dropped because it was full.",
+        );
+        for (name, stats) in &queues {
+            let _ = writeln!(
+                out,
+                "mcp_queue_dropped_total{{queue=\"{name}\"}} {}",
+                stats.dropped
+            );
+        }
+        out
+    }
+
+    /// A router serving [`Metrics::render`] at `/metrics`. Merge it into the app of an HTTP
+    /// transport, or serve it on a port of its own.
+    #[cfg(any(feature = "streamable-http", feature = "sse-server"))]
+    pub fn router(&self) -> axum::Router {
+        use axum::{http::header, routing::get};
+
+        let metrics = self.clone();
+        axum::Router::new().route(
+            "/metrics",
+            get(move || async move { ([(header::CONTENT_TYPE, CONTENT_TYPE)], metrics.render()) }),
+        )
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+/// Escapes a label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Keeps a session counted in `mcp_active_sessions` while it lives.
+pub struct ActiveSession(Arc<AtomicI64>);
+
+impl Drop for ActiveSession {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[async_trait]
+impl ToolMiddleware for Metrics {
+    async fn after_call(
+        &self,
+        call: &ToolCall,
+        result: Result<Vec<Content>>,
+    ) -> Result<Vec<Content>> {
+        let tool = match &call.tool {
+            Some(_) => call.name.as_str(),
+            None => UNKNOWN,
+        };
+        self.record_tool_call(tool, call.started_at.elapsed(), result.is_err());
+        result
+    }
+}
+
+impl<S> Layer<S> for Metrics {
+    type Service = MetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MetricsService {
+            inner,
+            metrics: self.clone(),
+        }
+    }
+}
+
+/// The service produced by a [`Metrics`] layer, counting requests and errors.
+#[derive(Clone)]
+pub struct MetricsService<S> {
+    inner: S,
+    metrics: Metrics,
+}
+
+impl<S> tower::Service<McpRequest> for MetricsService<S>
+where
+    S: tower::Service<McpRequest, Response = JsonRpcResponse>,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, std::result::Result<JsonRpcResponse, BoxError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), BoxError>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: McpRequest) -> Self::Future {
+        let method = req.request.method.clone();
+        let metrics = self.metrics.clone();
+        let response = self.inner.call(req);
+        Box::pin(async move {
+            let result = response.await.map_err(Into::into);
+            let error_code = match &result {
+                Ok(response) => response.error.as_ref().map(|error| error.code),
+                Err(e) => Some(match e.downcast_ref::<Error>() {
+                    Some(e) => ErrorData::from(e).code,
+                    None => INTERNAL_ERROR,
+                }),
+            };
+            metrics.record_request(&method, error_code);
+            result
+        })
+    }
+}
+
+#[cfg(all(test, any(feature = "streamable-http", feature = "sse-server")))]
+mod tests {
+    use serde_json::json;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::{
+        core::{
+            Tool,
+            protocol::message::{JsonRpcMessage, JsonRpcRequest},
+            utils::{QueueConfig, message_queue},
+        },
+        server::Server,
+        service::registry::{Registry, RegistryService},
+        transport::sse::SseTransport,
+    };
+
+    #[tokio::test]
+    async fn test_scrape_metrics_endpoint() {
+        let registry = Arc::new(Registry::new());
+        registry.add_tool(
+            Tool::new("echo", "", json!({ "type": "object" })),
+            |args, _| async move { Ok(vec![Content::text(args.to_string())]) },
+        );
+        let metrics = Metrics::new();
+        let server = Server::new(Box::new(RegistryService::new("metrics", "", registry)))
+            .with_metrics(metrics.clone());
+        let (to_client_tx, mut rx) = message_queue(QueueConfig::default());
+        let (tx, to_server_rx) = message_queue(QueueConfig::default());
+        let outbound = to_client_tx.clone();
+        metrics.register_queue("outbound", move || outbound.stats());
+        let _session = tokio::spawn(server.run(SseTransport::new(to_client_tx, to_server_rx)));
+
+        let requests = [
+            ("tools/call", json!({ "name": "echo", "arguments": {} })),
+            ("tools/call", json!({ "name": "missing" })),
+            ("nope", json!({})),
+        ];
+        for (id, (method, params)) in requests.into_iter().enumerate() {
+            let request = JsonRpcRequest::new(Some(id as u64), method, Some(params));
+            tx.send(JsonRpcMessage::Request(request)).await.unwrap();
+            rx.recv().await.unwrap();
+        }
+        // A method some other layer answered still only gets a label if dispatch knows it.
+        metrics.record_request("custom/method", None);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, metrics.router()).await });
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        for line in [
+            "mcp_requests_total{method=\"tools/call\"} 2",
+            "mcp_requests_total{method=\"unknown\"} 2",
+            "mcp_request_errors_total{method=\"unknown\",code=\"-32601\"} 1",
+            "mcp_tool_call_duration_seconds_bucket{tool=\"echo\",le=\"+Inf\"} 1",
+            "mcp_tool_call_duration_seconds_count{tool=\"unknown\"} 1",
+            "mcp_tool_errors_total{tool=\"unknown\"} 1",
+            "mcp_active_sessions 1",
+            "mcp_queue_depth{queue=\"outbound\"} 0",
+        ] {
+            assert!(
+                response.lines().any(|l| l == line),
+                "missing {line}:\n{response}"
+            );
+        }
+    }
+}
@@ -1,66 +1,407 @@
+use std::{collections::HashMap, future::Future, sync::Arc, time::Duration};
+
+use futures::{StreamExt, stream::FuturesUnordered};
+use serde_json::{Value, json};
+use tokio::{
+    sync::{broadcast, mpsc},
+    time::{Instant, sleep_until},
+};
+use tokio_util::sync::CancellationToken;
+use tower::{Layer, ServiceExt as _, util::BoxCloneService};
+use tracing::Instrument;
+
 use crate::{
-    core::protocol::{
-        constants::{INTERNAL_ERROR, INVALID_REQUEST, PARSE_ERROR},
-        error::ErrorData,
-        message::{JsonRpcError, JsonRpcMessage, JsonRpcRequest, JsonRpcResponse},
+    core::{
+        protocol::{
+            capabilities::ClientCapabilities,
+            constants::{INTERNAL_ERROR, JSONRPC_EXPECTED_VERSION, PROTOCOL_VERSION},
+            error::ErrorData,
+            message::{
+                JsonRpcError, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse,
+            },
+            result::Implementation,
+        },
+        utils::KeepaliveConfig,
     },
     error::{Error, Result},
-    service::{ext::ServiceExt, traits::Service},
-    transport::traits::ServerTransport,
+    metrics::Metrics,
+    service::{
+        context::{AuthIdentity, Peer, ProgressReporter, RequestContext},
+        layer::{BoxError, BoxMcpService, McpRequest, McpService},
+        limits::ToolLimits,
+        middleware::{ToolMiddleware, ToolPipeline},
+        pagination::Pagination,
+        traits::Service,
+    },
+    transport::{ByteTransport, new_session_id, stdio, traits::ServerTransport},
 };
 
+/// The data of the `notifications/message` that announces a shutdown to the client.
+pub const SHUTDOWN_NOTICE: &str = "Server is shutting down";
+
+/// How long in-flight requests may run once the server stops reading, unless configured with
+/// [`Server::with_drain_timeout`].
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+type LayerFn = Arc<dyn Fn(BoxMcpService) -> BoxMcpService + Send + Sync>;
+
 pub struct Server {
-    router: Box<dyn Service>,
+    router: Arc<dyn Service>,
+    keepalive: Option<KeepaliveConfig>,
+    pagination: Pagination,
+    layers: Vec<LayerFn>,
+    middlewares: Vec<Arc<dyn ToolMiddleware>>,
+    tool_limits: Option<ToolLimits>,
+    shutdown: Option<CancellationToken>,
+    drain_timeout: Duration,
+    metrics: Option<Metrics>,
 }
 
 impl Server {
     pub fn new(router: Box<dyn Service>) -> Self {
-        Self { router }
+        Self {
+            router: Arc::from(router),
+            keepalive: None,
+            pagination: Pagination::default(),
+            layers: Vec::new(),
+            middlewares: Vec::new(),
+            tool_limits: None,
+            shutdown: None,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+            metrics: None,
+        }
+    }
+
+    /// Wraps request dispatch in a tower [`Layer`], such as a timeout or a concurrency limit.
+    ///
+    /// Layers added later wrap the ones added before them. Errors raised by a layer are sent to
+    /// the client as JSON-RPC errors.
+    pub fn with_layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer<BoxMcpService> + Send + Sync + 'static,
+        L::Service: tower::Service<McpRequest, Response = JsonRpcResponse> + Clone + Send + 'static,
+        <L::Service as tower::Service<McpRequest>>::Error: Into<BoxError>,
+        <L::Service as tower::Service<McpRequest>>::Future: Send + 'static,
+    {
+        self.layers.push(Arc::new(move |inner| {
+            BoxCloneService::new(layer.layer(inner).map_err(Into::into))
+        }));
+        self
     }
 
-    pub async fn run(mut self, mut transport: impl ServerTransport) -> Result<()> {
-        let router = &mut *self.router;
+    /// Runs every `tools/call` through `middleware`, after the middlewares added before it.
+    pub fn with_middleware(mut self, middleware: impl ToolMiddleware + 'static) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// Enforces timeouts and concurrency limits on tool calls. Pass clones of one [`ToolLimits`]
+    /// to the servers of all sessions to share the limits between them.
+    pub fn with_tool_limits(mut self, limits: ToolLimits) -> Self {
+        self.tool_limits = Some(limits);
+        self
+    }
+
+    /// Records requests, errors, tool latencies and the session itself in `metrics`. It sees
+    /// every request, including those refused by layers, and every tool call, including those
+    /// answered by middlewares.
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Sets how list results are split into pages and how their cursors are signed.
+    pub fn with_pagination(mut self, pagination: Pagination) -> Self {
+        self.pagination = pagination;
+        self
+    }
+
+    /// Sets the maximum number of items returned by a single `*/list` call.
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.pagination = self.pagination.with_page_size(page_size);
+        self
+    }
+
+    /// Periodically pings the client and closes the transport when a ping goes unanswered.
+    pub fn with_keepalive(mut self, config: KeepaliveConfig) -> Self {
+        self.keepalive = Some(config);
+        self
+    }
+
+    /// Shuts the server down once `token` is cancelled, as [`Server::run_until`] does when its
+    /// future resolves.
+    pub fn with_shutdown(mut self, token: CancellationToken) -> Self {
+        self.shutdown = Some(token);
+        self
+    }
+
+    /// Bounds how long in-flight requests may run once the server stops reading, on shutdown or
+    /// when the client closes its side. Requests still running then are cancelled and answered
+    /// with an error. Defaults to [`DEFAULT_DRAIN_TIMEOUT`].
+    pub fn with_drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// The dispatch service wrapped in every configured layer.
+    fn service(&self) -> BoxMcpService {
+        let metrics = self
+            .metrics
+            .clone()
+            .map(|metrics| Arc::new(metrics) as Arc<dyn ToolMiddleware>);
+        let router =
+            if metrics.is_none() && self.middlewares.is_empty() && self.tool_limits.is_none() {
+                self.router.clone()
+            } else {
+                let mut pipeline = metrics
+                    .into_iter()
+                    .chain(self.middlewares.iter().cloned())
+                    .fold(ToolPipeline::new(self.router.clone()), |pipeline, m| {
+                        pipeline.with_middleware(m)
+                    });
+                if let Some(limits) = &self.tool_limits {
+                    pipeline = pipeline.with_limits(limits.clone());
+                }
+                Arc::new(pipeline)
+            };
+        let base = McpService::new(router, self.pagination.clone());
+        let base = BoxCloneService::new(tower::ServiceExt::<McpRequest>::map_err(
+            base,
+            BoxError::from,
+        ));
+        let service = self.layers.iter().fold(base, |inner, layer| layer(inner));
+        match &self.metrics {
+            Some(metrics) => BoxCloneService::new(metrics.layer(service)),
+            None => service,
+        }
+    }
+
+    /// Serves one session until the transport closes.
+    ///
+    /// Requests are processed concurrently, so a slow tool call does not hold up pings,
+    /// cancellations or the client's answers to requests made through [`Peer`].
+    pub async fn run(self, transport: impl ServerTransport) -> Result<()> {
+        self.run_until(transport, std::future::pending()).await
+    }
+
+    /// Like [`Server::run`], but also shuts down once `shutdown` resolves.
+    ///
+    /// Shutting down stops reading new messages and tells the client with a `notice` level
+    /// `notifications/message`. Requests already in flight are allowed to finish, within the
+    /// drain timeout, and their responses are written. Then the transport is closed.
+    pub async fn run_until(
+        self,
+        mut transport: impl ServerTransport,
+        shutdown: impl Future<Output = ()> + Send,
+    ) -> Result<()> {
+        let token = self.shutdown.clone().unwrap_or_default();
+        let shutdown = async move {
+            tokio::select! {
+                _ = shutdown => {}
+                _ = token.cancelled() => {}
+            }
+        };
+        tokio::pin!(shutdown);
+        let service = self.service();
+        let _active = self.metrics.as_ref().map(Metrics::track_session);
+        let mut keepalive = self.keepalive.map(Keepalive::new);
+        let mut notifications = self.router.subscribe_notifications();
+
+        let (outbound_tx, mut outbound_rx) = mpsc::unbounded_channel();
+        let peer = Peer::new(outbound_tx);
+        let mut session = Session::new(&transport);
+        let mut in_flight = FuturesUnordered::new();
+        let mut cancellations: HashMap<u64, CancellationToken> = HashMap::new();
+
+        let mut drain = true;
+        let mut shutting_down = false;
 
         tracing::info!("Server started");
-        while let Some(msg_result) = transport.read_message().await {
-            let _span = tracing::span!(tracing::Level::INFO, "message_processing");
-            let _enter = _span.enter();
+        loop {
+            let deadline = keepalive.as_ref().map(Keepalive::deadline);
+            let msg_result = tokio::select! {
+                msg = transport.read_message() => match msg {
+                    Some(msg) => msg,
+                    None => break,
+                },
+                _ = &mut shutdown => {
+                    tracing::info!("Shutdown requested, no longer accepting requests");
+                    shutting_down = true;
+                    break;
+                }
+                Some((id, response)) = in_flight.next(), if !in_flight.is_empty() => {
+                    if let Some(id) = id {
+                        cancellations.remove(&id);
+                    }
+                    if let Some(response) = response {
+                        Self::send_response(&mut transport, response).await?;
+                    }
+                    continue;
+                }
+                Some(msg) = outbound_rx.recv() => {
+                    transport.write_message(msg).await?;
+                    continue;
+                }
+                _ = sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    let Some(keepalive) = keepalive.as_mut() else { continue };
+                    if keepalive.is_expired() {
+                        tracing::warn!("Client did not answer ping in time, closing transport");
+                        transport.close().await?;
+                        drain = false;
+                        break;
+                    }
+                    let ping = keepalive.next_ping(peer.next_id());
+                    transport.write_message(JsonRpcMessage::Request(ping)).await?;
+                    continue;
+                }
+                notification = next_notification(&mut notifications), if notifications.is_some() => {
+                    match notification {
+                        Some(notification) => {
+                            transport
+                                .write_message(JsonRpcMessage::Notification(notification))
+                                .await?;
+                        }
+                        None => notifications = None,
+                    }
+                    continue;
+                }
+            };
 
             match msg_result {
-                Ok(msg) => {
-                    Self::handle_message(router, &mut transport, msg).await?;
+                Ok(JsonRpcMessage::Request(request)) => {
+                    if request.method == "initialize" {
+                        session.initialize(request.params.as_ref());
+                    }
+                    let ctx = session.context(&request, &peer);
+                    if let Some(id) = request.id {
+                        cancellations.insert(id, ctx.cancellation.clone());
+                    }
+
+                    let span = tracing::span!(tracing::Level::INFO, "message_processing");
+                    in_flight.push(
+                        Self::process_cancellable(service.clone(), request, ctx).instrument(span),
+                    );
+                }
+                Ok(JsonRpcMessage::Response(response)) => {
+                    if !acknowledge(&mut keepalive, response.id) {
+                        peer.resolve(response.id, response.result, response.error);
+                    }
+                }
+                Ok(JsonRpcMessage::Error(error)) => {
+                    if !acknowledge(&mut keepalive, error.id) {
+                        peer.resolve(error.id, None, Some(error.error));
+                    }
+                }
+                Ok(JsonRpcMessage::Notification(notification)) => {
+                    Self::handle_notification(&cancellations, notification);
                 }
+                Ok(JsonRpcMessage::Nil) => {}
                 Err(e) => {
                     Self::handle_error(&mut transport, e).await?;
                 }
             }
         }
         tracing::info!("Server transport closed, exiting run loop");
+        // Nothing is read anymore, so requests made through the peer can never be answered.
+        peer.fail_pending();
 
+        if shutting_down {
+            let notice = JsonRpcNotification::new(
+                "notifications/message",
+                Some(json!({ "level": "notice", "logger": "server", "data": SHUTDOWN_NOTICE })),
+            );
+            // The client may already be gone, which must not keep the server from draining.
+            if let Err(e) = transport
+                .write_message(JsonRpcMessage::Notification(notice))
+                .await
+            {
+                tracing::debug!(error = %e, "Could not tell the client about the shutdown");
+            }
+        }
+
+        if drain && !in_flight.is_empty() {
+            tracing::info!(
+                in_flight = in_flight.len(),
+                "Waiting for in-flight requests"
+            );
+            let deadline = Instant::now() + self.drain_timeout;
+            while !in_flight.is_empty() {
+                tokio::select! {
+                    Some((id, response)) = in_flight.next() => {
+                        if let Some(id) = id {
+                            cancellations.remove(&id);
+                        }
+                        if let Some(response) = response {
+                            Self::send_response(&mut transport, response).await?;
+                        }
+                    }
+                    Some(msg) = outbound_rx.recv() => {
+                        transport.write_message(msg).await?;
+                    }
+                    _ = sleep_until(deadline) => {
+                        tracing::warn!(
+                            in_flight = in_flight.len(),
+                            "Drain timeout elapsed, cancelling remaining requests"
+                        );
+                        for id in cancellations.keys() {
+                            let error = ErrorData::new(
+                                INTERNAL_ERROR,
+                                "Server shut down before the request completed",
+                            );
+                            let response = JsonRpcResponse::error(Some(*id), error);
+                            Self::send_response(&mut transport, response).await?;
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+
+        for token in cancellations.values() {
+            token.cancel();
+        }
+        if shutting_down {
+            transport.close().await?;
+        }
         Ok(())
     }
 
-    async fn handle_message(
-        router: &mut dyn Service,
-        transport: &mut impl ServerTransport,
-        msg: JsonRpcMessage,
-    ) -> Result<()> {
-        match msg {
-            JsonRpcMessage::Request(request) => {
-                let response = Self::process_request(router, request).await;
-                Self::send_response(transport, response).await?;
-            }
-            JsonRpcMessage::Response(_)
-            | JsonRpcMessage::Notification(_)
-            | JsonRpcMessage::Nil
-            | JsonRpcMessage::Error(_) => {
-                // TODO: Handle other message types
+    /// Serves a single client over stdin and stdout.
+    ///
+    /// Logs go to stderr so they never corrupt the protocol stream. The server stops on EOF,
+    /// SIGINT or SIGTERM, after in-flight requests have been answered.
+    pub async fn run_stdio(self) -> Result<()> {
+        stdio::init_tracing();
+        self.run_until(ByteTransport::stdio(), stdio::shutdown_signal())
+            .await
+    }
+
+    /// Processes a request unless the client cancels it first. Cancelled requests get no
+    /// response.
+    async fn process_cancellable(
+        service: BoxMcpService,
+        request: JsonRpcRequest,
+        ctx: RequestContext,
+    ) -> (Option<u64>, Option<JsonRpcResponse>) {
+        let id = request.id;
+        let cancellation = ctx.cancellation.clone();
+        // Check cancellation first: a handler that stops because it saw the token must not get
+        // its result sent.
+        tokio::select! {
+            biased;
+            _ = cancellation.cancelled() => {
+                tracing::debug!(request_id = ?id, "Request cancelled");
+                (id, None)
             }
+            response = Self::process_request(service, request, ctx) => (id, Some(response)),
         }
-        Ok(())
     }
 
-    async fn process_request(router: &dyn Service, request: JsonRpcRequest) -> JsonRpcResponse {
+    async fn process_request(
+        service: BoxMcpService,
+        request: JsonRpcRequest,
+        ctx: RequestContext,
+    ) -> JsonRpcResponse {
         let id = request.id;
         let request_json = serde_json::to_string(&request)
             .unwrap_or_else(|_| "Failed to serialize request".to_string());
@@ -72,44 +413,36 @@ impl Server {
             "Received request"
         );
 
-        let result = match request.method.as_str() {
-            "initialize" => router.handle_initialize(request).await,
-            "tools/list" => router.handle_tools_list(request).await,
-            "tools/call" => router.handle_tools_call(request).await,
-            "resources/list" => router.handle_resources_list(request).await,
-            "resources/read" => router.handle_resources_read(request).await,
-            "prompts/list" => router.handle_prompts_list(request).await,
-            "prompts/get" => router.handle_prompts_get(request).await,
-            _ => {
-                let mut response = router.create_response(id);
-                response.error = Some(ErrorData {
-                    code: INVALID_REQUEST,
-                    message: format!("Method '{}' not found", request.method),
-                    data: None,
-                });
-                return response;
-            }
-        };
-
-        match result {
+        match service.oneshot(McpRequest { request, ctx }).await {
             Ok(resp) => resp,
             Err(e) => {
-                let error_msg = e.to_string();
-                tracing::error!(error = %error_msg, "Request processing failed");
-                JsonRpcResponse {
-                    jsonrpc: "2.0".to_string(),
-                    id,
-                    result: None,
-                    error: Some(ErrorData {
-                        code: INTERNAL_ERROR,
-                        message: error_msg,
-                        data: None,
-                    }),
-                }
+                tracing::error!(error = %e, "Request processing failed");
+                let error = match e.downcast::<Error>() {
+                    Ok(e) => ErrorData::from(&*e),
+                    Err(e) => ErrorData::new(INTERNAL_ERROR, e.to_string()),
+                };
+                JsonRpcResponse::error(id, error)
             }
         }
     }
 
+    fn handle_notification(
+        cancellations: &HashMap<u64, CancellationToken>,
+        notification: JsonRpcNotification,
+    ) {
+        if notification.method != "notifications/cancelled" {
+            return;
+        }
+        let request_id = notification
+            .params
+            .as_ref()
+            .and_then(|p| p.get("requestId"))
+            .and_then(Value::as_u64);
+        if let Some(token) = request_id.and_then(|id| cancellations.get(&id)) {
+            token.cancel();
+        }
+    }
+
     async fn send_response(
         transport: &mut impl ServerTransport,
         response: JsonRpcResponse,
@@ -129,30 +462,533 @@ impl Server {
     }
 
     async fn handle_error(transport: &mut impl ServerTransport, e: Error) -> Result<()> {
-        let error = match e {
-            Error::Json(_) | Error::InvalidMessage(_) => ErrorData {
-                code: PARSE_ERROR,
-                message: e.to_string(),
-                data: None,
-            },
-            Error::Protocol(_) => ErrorData {
-                code: INVALID_REQUEST,
-                message: e.to_string(),
-                data: None,
-            },
-            _ => ErrorData {
-                code: INTERNAL_ERROR,
-                message: e.to_string(),
-                data: None,
-            },
+        // Echo the request id back whenever the transport managed to recover it.
+        let id = match &e {
+            Error::InvalidRequest { id, .. } => *id,
+            _ => None,
         };
 
         let error_response = JsonRpcMessage::Error(JsonRpcError {
-            jsonrpc: "2.0".to_string(),
-            id: None,
-            error,
+            jsonrpc: JSONRPC_EXPECTED_VERSION.to_string(),
+            id,
+            error: ErrorData::from(&e),
         });
 
         transport.write_message(error_response).await
     }
 }
+
+/// What the server knows about the client of one session.
+struct Session {
+    id: String,
+    auth: Option<AuthIdentity>,
+    protocol_version: Option<String>,
+    client_capabilities: Option<ClientCapabilities>,
+    client_info: Option<Implementation>,
+}
+
+impl Session {
+    fn new(transport: &impl ServerTransport) -> Self {
+        Self {
+            // Transports without sessions of their own still get an id, so that per-session
+            // state such as rate limits never lumps separate connections together.
+            id: transport.session_id().unwrap_or_else(new_session_id),
+            auth: transport.auth_identity(),
+            protocol_version: None,
+            client_capabilities: None,
+            client_info: None,
+        }
+    }
+
+    fn initialize(&mut self, params: Option<&Value>) {
+        let field = |name: &str| params.and_then(|p| p.get(name)).cloned();
+
+        // The server only speaks one version, so that is what gets negotiated.
+        self.protocol_version = Some(PROTOCOL_VERSION.to_string());
+        self.client_capabilities =
+            field("capabilities").and_then(|v| serde_json::from_value(v).ok());
+        self.client_info = field("clientInfo").and_then(|v| serde_json::from_value(v).ok());
+    }
+
+    fn context(&self, request: &JsonRpcRequest, peer: &Peer) -> RequestContext {
+        let progress_token = request
+            .params
+            .as_ref()
+            .and_then(|p| p.get("_meta"))
+            .and_then(|meta| meta.get("progressToken"))
+            .cloned();
+
+        RequestContext {
+            request_id: request.id,
+            session_id: Some(self.id.clone()),
+            protocol_version: self.protocol_version.clone(),
+            client_capabilities: self.client_capabilities.clone(),
+            client_info: self.client_info.clone(),
+            auth: self.auth.clone(),
+            cancellation: CancellationToken::new(),
+            progress: ProgressReporter::new(progress_token, peer.clone()),
+            peer: peer.clone(),
+        }
+    }
+}
+
+/// Waits for the next service notification. Returns `None` once the sender is gone.
+async fn next_notification(
+    receiver: &mut Option<broadcast::Receiver<JsonRpcNotification>>,
+) -> Option<JsonRpcNotification> {
+    let receiver = receiver.as_mut()?;
+    loop {
+        match receiver.recv().await {
+            Ok(notification) => return Some(notification),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::warn!(skipped, "Session fell behind, dropping notifications");
+            }
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+}
+
+/// Returns whether `id` answered the outstanding keepalive ping.
+fn acknowledge(keepalive: &mut Option<Keepalive>, id: Option<u64>) -> bool {
+    keepalive.as_mut().is_some_and(|k| k.acknowledge(id))
+}
+
+/// Tracks the outstanding keepalive ping for a single connection.
+struct Keepalive {
+    config: KeepaliveConfig,
+    next_ping_at: Instant,
+    pending: Option<(u64, Instant)>,
+}
+
+impl Keepalive {
+    fn new(config: KeepaliveConfig) -> Self {
+        Self {
+            config,
+            next_ping_at: Instant::now() + config.interval,
+            pending: None,
+        }
+    }
+
+    /// The instant at which either the next ping is due or the pending one times out.
+    fn deadline(&self) -> Instant {
+        match self.pending {
+            Some((_, sent_at)) => sent_at + self.config.timeout,
+            None => self.next_ping_at,
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        self.pending.is_some() && Instant::now() >= self.deadline()
+    }
+
+    fn next_ping(&mut self, id: u64) -> JsonRpcRequest {
+        let now = Instant::now();
+        self.pending = Some((id, now));
+        self.next_ping_at = now + self.config.interval;
+        JsonRpcRequest::new(Some(id), "ping", None)
+    }
+
+    fn acknowledge(&mut self, id: Option<u64>) -> bool {
+        let matched = matches!(self.pending, Some((pending, _)) if Some(pending) == id);
+        if matched {
+            self.pending = None;
+        }
+        matched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        core::{
+            protocol::constants::METHOD_NOT_FOUND,
+            utils::{QueueConfig, QueueReceiver, QueueSender, message_queue},
+        },
+        service::impls::counter::CounterRouter,
+        transport::sse::SseTransport,
+    };
+
+    fn spawn_server(
+        server: Server,
+    ) -> (
+        QueueSender,
+        QueueReceiver,
+        tokio::task::JoinHandle<Result<()>>,
+    ) {
+        let (to_client_tx, to_client_rx) = message_queue(QueueConfig::default());
+        let (to_server_tx, to_server_rx) = message_queue(QueueConfig::default());
+        let transport = SseTransport::new(to_client_tx, to_server_rx);
+        let handle = tokio::spawn(server.run(transport));
+        (to_server_tx, to_client_rx, handle)
+    }
+
+    #[tokio::test]
+    async fn test_ping_and_unknown_method() {
+        let server = Server::new(Box::new(CounterRouter::new()));
+        let (tx, mut rx, _handle) = spawn_server(server);
+
+        tx.send(JsonRpcMessage::Request(JsonRpcRequest::new(
+            Some(1),
+            "ping",
+            None,
+        )))
+        .await
+        .unwrap();
+        match rx.recv().await.unwrap() {
+            JsonRpcMessage::Response(response) => {
+                assert_eq!(response.id, Some(1));
+                assert_eq!(response.result, Some(json!({})));
+            }
+            other => panic!("Expected Response, got {other:?}"),
+        }
+
+        tx.send(JsonRpcMessage::Request(JsonRpcRequest::new(
+            Some(2),
+            "nope",
+            None,
+        )))
+        .await
+        .unwrap();
+        match rx.recv().await.unwrap() {
+            JsonRpcMessage::Response(response) => {
+                assert_eq!(response.id, Some(2));
+                assert_eq!(response.error.unwrap().code, METHOD_NOT_FOUND);
+            }
+            other => panic!("Expected Response, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prompt_error_codes_reach_client() {
+        use std::sync::Arc;
+
+        use crate::{
+            core::prompt::Prompt,
+            service::registry::{Registry, RegistryService},
+        };
+
+        let registry = Arc::new(Registry::new());
+        registry.add_prompt(Prompt::new("quota", None::<String>, None), |_| async {
+            Err(Error::rpc(-32001, "Quota exceeded"))
+        });
+        let service = RegistryService::new("prompts", "", registry);
+        let (tx, mut rx, _handle) = spawn_server(Server::new(Box::new(service)));
+
+        tx.send(JsonRpcMessage::Request(JsonRpcRequest::new(
+            Some(1),
+            "prompts/get",
+            Some(json!({ "name": "quota", "arguments": {} })),
+        )))
+        .await
+        .unwrap();
+        match rx.recv().await.unwrap() {
+            JsonRpcMessage::Response(response) => {
+                let error = response.error.unwrap();
+                assert_eq!(error.code, -32001);
+                assert_eq!(error.message, "Quota exceeded");
+            }
+            other => panic!("Expected Response, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registry_changes_notify_session() {
+        use std::sync::Arc;
+
+        use crate::{
+            core::{Tool, content::Content},
+            service::registry::{Registry, RegistryService},
+        };
+
+        let registry = Arc::new(Registry::new());
+        let service = RegistryService::new("dynamic", "", registry.clone());
+        let (tx, mut rx, _handle) = spawn_server(Server::new(Box::new(service)));
+
+        // Round-trip a ping so the session has subscribed before the registry changes.
+        tx.send(JsonRpcMessage::Request(JsonRpcRequest::new(
+            Some(1),
+            "ping",
+            None,
+        )))
+        .await
+        .unwrap();
+        assert!(matches!(rx.recv().await, Some(JsonRpcMessage::Response(_))));
+
+        registry.add_tool(
+            Tool::new("echo", "Echo", json!({ "type": "object" })),
+            |args, _ctx| async move { Ok(vec![Content::text(args.to_string())]) },
+        );
+        match rx.recv().await.unwrap() {
+            JsonRpcMessage::Notification(notification) => {
+                assert_eq!(notification.method, "notifications/tools/list_changed");
+            }
+            other => panic!("Expected Notification, got {other:?}"),
+        }
+
+        tx.send(JsonRpcMessage::Request(JsonRpcRequest::new(
+            Some(2),
+            "tools/list",
+            None,
+        )))
+        .await
+        .unwrap();
+        match rx.recv().await.unwrap() {
+            JsonRpcMessage::Response(response) => {
+                assert_eq!(response.result.unwrap()["tools"][0]["name"], "echo");
+            }
+            other => panic!("Expected Response, got {other:?}"),
+        }
+
+        assert!(registry.remove_tool("echo"));
+        assert!(!registry.remove_tool("echo"));
+        match rx.recv().await.unwrap() {
+            JsonRpcMessage::Notification(notification) => {
+                assert_eq!(notification.method, "notifications/tools/list_changed");
+            }
+            other => panic!("Expected Notification, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_context_peer_request_and_cancellation() {
+        use std::sync::Arc;
+
+        use crate::{
+            core::{Tool, content::Content},
+            service::registry::{Registry, RegistryService},
+        };
+
+        let registry = Arc::new(Registry::new());
+        let schema = json!({ "type": "object" });
+        registry.add_tool(
+            Tool::new("roots", "", schema.clone()),
+            |_, ctx| async move {
+                let roots = ctx.peer.request("roots/list", None).await?;
+                Ok(vec![Content::text(roots.to_string())])
+            },
+        );
+        registry.add_tool(Tool::new("sleep", "", schema), |_, ctx| async move {
+            ctx.cancellation.cancelled().await;
+            Ok(vec![])
+        });
+        let service = RegistryService::new("dynamic", "", registry);
+        let (tx, mut rx, _handle) = spawn_server(Server::new(Box::new(service)));
+
+        let call = |id, name| {
+            JsonRpcMessage::Request(JsonRpcRequest::new(
+                Some(id),
+                "tools/call",
+                Some(json!({ "name": name })),
+            ))
+        };
+
+        // The tool's request to the client is answered while the call is still in flight.
+        tx.send(call(1, "roots")).await.unwrap();
+        let peer_request = match rx.recv().await.unwrap() {
+            JsonRpcMessage::Request(request) => request,
+            other => panic!("Expected Request, got {other:?}"),
+        };
+        assert_eq!(peer_request.method, "roots/list");
+        tx.send(JsonRpcMessage::Response(JsonRpcResponse::success(
+            peer_request.id,
+            json!({ "roots": [] }),
+        )))
+        .await
+        .unwrap();
+        match rx.recv().await.unwrap() {
+            JsonRpcMessage::Response(response) => {
+                assert_eq!(response.id, Some(1));
+                assert_eq!(
+                    response.result.unwrap()["content"][0]["text"],
+                    r#"{"roots":[]}"#
+                );
+            }
+            other => panic!("Expected Response, got {other:?}"),
+        }
+
+        // A cancelled request gets no response, and the session keeps serving.
+        tx.send(call(2, "sleep")).await.unwrap();
+        tx.send(JsonRpcMessage::Notification(JsonRpcNotification::new(
+            "notifications/cancelled",
+            Some(json!({ "requestId": 2 })),
+        )))
+        .await
+        .unwrap();
+        tx.send(JsonRpcMessage::Request(JsonRpcRequest::new(
+            Some(3),
+            "ping",
+            None,
+        )))
+        .await
+        .unwrap();
+        match rx.recv().await.unwrap() {
+            JsonRpcMessage::Response(response) => assert_eq!(response.id, Some(3)),
+            other => panic!("Expected Response, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drains_in_flight_requests_on_eof() {
+        use std::{sync::Arc, time::Duration};
+
+        use crate::{
+            core::{Tool, content::Content},
+            service::registry::{Registry, RegistryService},
+        };
+
+        let registry = Arc::new(Registry::new());
+        registry.add_tool(
+            Tool::new("slow", "", json!({ "type": "object" })),
+            |_, _| async move {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                Ok(vec![Content::text("done")])
+            },
+        );
+        let service = RegistryService::new("dynamic", "", registry);
+        let (tx, mut rx, handle) = spawn_server(Server::new(Box::new(service)));
+
+        tx.send(JsonRpcMessage::Request(JsonRpcRequest::new(
+            Some(1),
+            "tools/call",
+            Some(json!({ "name": "slow" })),
+        )))
+        .await
+        .unwrap();
+        drop(tx);
+
+        match rx.recv().await.unwrap() {
+            JsonRpcMessage::Response(response) => assert_eq!(response.id, Some(1)),
+            other => panic!("Expected Response, got {other:?}"),
+        }
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_eof_fails_pending_peer_requests() {
+        use std::sync::Arc;
+
+        use crate::{
+            core::{Tool, content::Content},
+            service::registry::{Registry, RegistryService},
+        };
+
+        let registry = Arc::new(Registry::new());
+        registry.add_tool(
+            Tool::new("roots", "", json!({ "type": "object" })),
+            |_, ctx| async move {
+                let roots = ctx.peer.request("roots/list", None).await?;
+                Ok(vec![Content::text(roots.to_string())])
+            },
+        );
+        let service = RegistryService::new("dynamic", "", registry);
+        let (tx, mut rx, handle) = spawn_server(Server::new(Box::new(service)));
+
+        tx.send(JsonRpcMessage::Request(JsonRpcRequest::new(
+            Some(1),
+            "tools/call",
+            Some(json!({ "name": "roots" })),
+        )))
+        .await
+        .unwrap();
+        match rx.recv().await.unwrap() {
+            JsonRpcMessage::Request(request) => assert_eq!(request.method, "roots/list"),
+            other => panic!("Expected Request, got {other:?}"),
+        }
+        // The client goes away without answering.
+        drop(tx);
+
+        match rx.recv().await.unwrap() {
+            JsonRpcMessage::Response(response) => {
+                assert_eq!(response.id, Some(1));
+                assert_eq!(response.result.unwrap()["isError"], true);
+            }
+            other => panic!("Expected Response, got {other:?}"),
+        }
+        tokio::time::timeout(Duration::from_secs(1), handle)
+            .await
+            .expect("server did not stop")
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_closes_unresponsive_client() {
+        let config = KeepaliveConfig::new(Duration::from_millis(20), Duration::from_millis(20));
+        let server = Server::new(Box::new(CounterRouter::new())).with_keepalive(config);
+        let (_tx, mut rx, handle) = spawn_server(server);
+
+        match rx.recv().await.unwrap() {
+            JsonRpcMessage::Request(request) => assert_eq!(request.method, "ping"),
+            other => panic!("Expected ping, got {other:?}"),
+        }
+
+        // The ping is never answered, so the run loop must give up on its own.
+        tokio::time::timeout(Duration::from_secs(1), handle)
+            .await
+            .expect("server did not stop")
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_within_timeout() {
+        use std::sync::Arc;
+
+        use crate::{
+            core::{Tool, content::Content, protocol::constants::INTERNAL_ERROR},
+            service::registry::{Registry, RegistryService},
+            shutdown::ShutdownCoordinator,
+        };
+
+        let (started_tx, mut started_rx) = mpsc::unbounded_channel();
+        let registry = Arc::new(Registry::new());
+        registry.add_tool(
+            Tool::new("slow", "", json!({ "type": "object" })),
+            move |_, _| {
+                let _ = started_tx.send(());
+                async move {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Ok(vec![Content::text("done")])
+                }
+            },
+        );
+        let coordinator = ShutdownCoordinator::new();
+        let server = Server::new(Box::new(RegistryService::new("slow", "", registry)))
+            .with_shutdown(coordinator.token())
+            .with_drain_timeout(Duration::from_millis(50));
+        let (to_client_tx, mut rx) = message_queue(QueueConfig::default());
+        let (tx, to_server_rx) = message_queue(QueueConfig::default());
+        let handle = coordinator.track(server.run(SseTransport::new(to_client_tx, to_server_rx)));
+
+        tx.send(JsonRpcMessage::Request(JsonRpcRequest::new(
+            Some(1),
+            "tools/call",
+            Some(json!({ "name": "slow" })),
+        )))
+        .await
+        .unwrap();
+        started_rx.recv().await.unwrap();
+
+        assert!(coordinator.shutdown(Duration::from_secs(1)).await);
+        handle.await.unwrap().unwrap();
+        match rx.recv().await.unwrap() {
+            JsonRpcMessage::Notification(notification) => {
+                assert_eq!(notification.method, "notifications/message");
+                assert_eq!(notification.params.unwrap()["data"], SHUTDOWN_NOTICE);
+            }
+            other => panic!("Expected Notification, got {other:?}"),
+        }
+        match rx.recv().await.unwrap() {
+            JsonRpcMessage::Response(response) => {
+                assert_eq!(response.id, Some(1));
+                assert_eq!(response.error.unwrap().code, INTERNAL_ERROR);
+            }
+            other => panic!("Expected Response, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,311 @@
+//! A record of every tool call, written off the request path.
+
+use std::{
+    collections::HashSet,
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::{Mutex, mpsc},
+};
+
+use crate::{
+    core::{content::Content, protocol::result::Implementation},
+    error::{Error, Result},
+    service::middleware::{ToolCall, ToolMiddleware},
+};
+
+/// Records buffered for the sink before new ones are dropped.
+pub const DEFAULT_AUDIT_BUFFER: usize = 1024;
+
+/// Replaces the values of redacted arguments.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Argument names redacted when nothing else is configured.
+pub const DEFAULT_REDACTED_KEYS: &[&str] = &[
+    "password",
+    "secret",
+    "token",
+    "access_token",
+    "api_key",
+    "apikey",
+    "authorization",
+];
+
+/// One tool call, as written to an [`AuditSink`].
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct AuditRecord {
+    pub timestamp: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client: Option<Implementation>,
+    /// The authenticated caller, if the transport established one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    pub tool: String,
+    pub arguments: Value,
+    pub duration_ms: u64,
+    /// Whether the tool reported a failure. Absent when the call failed with a JSON-RPC error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_error: Option<bool>,
+    /// The JSON-RPC error code the call failed with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<i32>,
+    /// The call was dropped before it finished: cancelled by the client, cut off at shutdown or
+    /// by a timeout.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
+}
+
+/// Where audit records end up.
+#[async_trait]
+pub trait AuditSink: Send + Sync + 'static {
+    async fn write(&self, record: &AuditRecord) -> Result<()>;
+}
+
+/// Appends one JSON object per line to a file.
+pub struct JsonLinesSink {
+    file: Mutex<File>,
+}
+
+impl JsonLinesSink {
+    /// Opens `path` for appending, creating it if needed.
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+#[async_trait]
+impl AuditSink for JsonLinesSink {
+    async fn write(&self, record: &AuditRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        let mut file = self.file.lock().await;
+        file.write_all(&line).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// A [`ToolMiddleware`] that sends an [`AuditRecord`] for every tool call to an [`AuditSink`].
+///
+/// Records are queued and written by a background task, so a slow sink never delays a call; when
+/// the queue is full, records are dropped and counted. Add it before other middlewares to also
+/// record calls that they refuse or answer. Calls dropped before they finish are recorded as
+/// cancelled. Clones share the queue and the sink.
+#[derive(Clone)]
+pub struct AuditLog {
+    records: mpsc::Sender<AuditRecord>,
+    redacted_keys: Arc<HashSet<String>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl AuditLog {
+    /// Starts writing to `sink`. Must be called from within a Tokio runtime.
+    pub fn new(sink: impl AuditSink) -> Self {
+        Self::with_buffer(sink, DEFAULT_AUDIT_BUFFER)
+    }
+
+    pub fn with_buffer(sink: impl AuditSink, buffer: usize) -> Self {
+        let (records, mut rx) = mpsc::channel::<AuditRecord>(buffer.max(1));
+        tokio::spawn(async move {
+            while let Some(record) = rx.recv().await {
+                if let Err(e) = sink.write(&record).await {
+                    tracing::warn!(?e, tool = %record.tool, "Failed to write audit record");
+                }
+            }
+        });
+        Self {
+            records,
+            redacted_keys: Arc::new(
+                DEFAULT_REDACTED_KEYS
+                    .iter()
+                    .map(|k| k.to_string())
+                    .collect(),
+            ),
+            dropped: Default::default(),
+        }
+    }
+
+    /// Replaces the argument names whose values are redacted, at any depth. Names are matched
+    /// case-insensitively.
+    pub fn with_redacted_keys<I, S>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.redacted_keys = Arc::new(
+            keys.into_iter()
+                .map(|k| k.as_ref().to_ascii_lowercase())
+                .collect(),
+        );
+        self
+    }
+
+    /// Records lost because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn record(&self, call: &ToolCall, outcome: impl FnOnce(&mut AuditRecord)) {
+        let mut arguments = call.arguments.clone();
+        self.redact(&mut arguments);
+        let mut record = AuditRecord {
+            timestamp: Utc::now(),
+            session_id: call.ctx.session_id.clone(),
+            client: call.ctx.client_info.clone(),
+            subject: call.ctx.auth.as_ref().map(|auth| auth.subject.clone()),
+            tool: call.name.clone(),
+            arguments,
+            duration_ms: call.started_at.elapsed().as_millis() as u64,
+            is_error: None,
+            error_code: None,
+            cancelled: false,
+        };
+        outcome(&mut record);
+        if self.records.try_send(record).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn redact(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if self.redacted_keys.contains(&key.to_ascii_lowercase()) {
+                        *value = Value::String(REDACTED.to_string());
+                    } else {
+                        self.redact(value);
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact(item)),
+            _ => {}
+        }
+    }
+}
+
+#[async_trait]
+impl ToolMiddleware for AuditLog {
+    async fn after_call(
+        &self,
+        call: &ToolCall,
+        result: Result<Vec<Content>>,
+    ) -> Result<Vec<Content>> {
+        self.record(call, |record| match &result {
+            Ok(_) => record.is_error = Some(false),
+            Err(Error::Rpc { code, .. }) => record.error_code = Some(*code),
+            Err(_) => record.is_error = Some(true),
+        });
+        result
+    }
+
+    fn call_aborted(&self, call: &ToolCall) {
+        self.record(call, |record| record.cancelled = true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        core::Tool,
+        service::{
+            context::RequestContext,
+            middleware::ToolPipeline,
+            registry::{Registry, RegistryService},
+            traits::Service,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_json_lines_audit_log() {
+        let path = std::env::temp_dir().join(format!("mcp-audit-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let registry = Arc::new(Registry::new());
+        registry.add_tool(
+            Tool::new("login", "", json!({ "type": "object" })),
+            |args, _| async move {
+                match args["user"].as_str() {
+                    Some("root") => Err(Error::rpc(-32001, "Forbidden")),
+                    _ => Ok(vec![Content::text("ok")]),
+                }
+            },
+        );
+        registry.add_tool(
+            Tool::new("hang", "", json!({ "type": "object" })),
+            |_, _| std::future::pending(),
+        );
+        let audit = AuditLog::new(JsonLinesSink::open(&path).await.unwrap());
+        let pipeline = ToolPipeline::new(Arc::new(RegistryService::new("audit", "", registry)))
+            .with_middleware(Arc::new(audit));
+
+        let ctx = RequestContext {
+            session_id: Some("s1".into()),
+            ..Default::default()
+        };
+        let args = json!({ "user": "alice", "auth": { "Password": "hunter2" } });
+        pipeline
+            .call_tool("login", args, ctx.clone())
+            .await
+            .unwrap();
+        let args = json!({ "user": "root" });
+        pipeline
+            .call_tool("login", args, ctx.clone())
+            .await
+            .unwrap_err();
+        let hang = pipeline.call_tool("hang", json!({}), ctx);
+        tokio::time::timeout(Duration::from_millis(10), hang)
+            .await
+            .unwrap_err();
+
+        let mut lines = Vec::new();
+        for _ in 0 .. 100 {
+            let text = std::fs::read_to_string(&path).unwrap_or_default();
+            lines = text.lines().map(String::from).collect();
+            if lines.len() == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        let records: Vec<Value> = lines
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0]["session_id"], "s1");
+        assert_eq!(records[0]["tool"], "login");
+        assert_eq!(records[0]["arguments"]["auth"]["Password"], REDACTED);
+        assert_eq!(records[0]["is_error"], false);
+        assert_eq!(records[1]["error_code"], -32001);
+        assert!(records[1].get("is_error").is_none());
+        assert!(records[1].get("cancelled").is_none());
+        assert_eq!(records[2]["tool"], "hang");
+        assert_eq!(records[2]["cancelled"], true);
+    }
+}
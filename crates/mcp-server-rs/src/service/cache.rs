@@ -0,0 +1,348 @@
+//! A tool-call middleware that reuses the results of read-only and idempotent tools.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::{
+    core::content::Content,
+    error::Result,
+    service::middleware::{ToolCall, ToolMiddleware},
+};
+
+/// Results kept when nothing else is configured.
+pub const DEFAULT_CACHE_CAPACITY: usize = 256;
+
+/// How long a result is reused when nothing else is configured.
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Counters of a [`ToolCache`], for metrics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Results dropped to make room, not counting expired ones.
+    pub evictions: u64,
+    pub entries: usize,
+}
+
+struct Entry {
+    content: Vec<Content>,
+    expires_at: Instant,
+    /// Position in the recency order.
+    tick: u64,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<String, Entry>,
+    /// Keys from least to most recently used.
+    recency: BTreeMap<u64, String>,
+    next_tick: u64,
+    stats: CacheStats,
+}
+
+impl State {
+    fn get(&mut self, key: &str) -> Option<Vec<Content>> {
+        let entry = self.entries.get_mut(key)?;
+        if entry.expires_at <= Instant::now() {
+            let entry = self.entries.remove(key)?;
+            self.recency.remove(&entry.tick);
+            return None;
+        }
+        self.recency.remove(&entry.tick);
+        entry.tick = self.next_tick;
+        self.recency.insert(entry.tick, key.to_string());
+        self.next_tick += 1;
+        Some(entry.content.clone())
+    }
+
+    fn insert(&mut self, key: String, content: Vec<Content>, ttl: Duration, capacity: usize) {
+        if let Some(entry) = self.entries.remove(&key) {
+            self.recency.remove(&entry.tick);
+        }
+        while self.entries.len() >= capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+            self.stats.evictions += 1;
+        }
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        self.recency.insert(tick, key.clone());
+        self.entries.insert(
+            key,
+            Entry {
+                content,
+                expires_at: Instant::now() + ttl,
+                tick,
+            },
+        );
+    }
+}
+
+/// Caches tool results by tool name and arguments, with a TTL and least-recently-used eviction.
+///
+/// Only tools annotated as read-only or idempotent are cached, unless overridden with
+/// [`ToolCache::with_tool`], and failed calls never are. Keys include the authenticated subject,
+/// or the session of an unauthenticated caller, so callers never see each other's results. Clones
+/// share their entries. Add the cache after middlewares that rewrite calls, so that it sees the
+/// calls as the tools do.
+#[derive(Clone)]
+pub struct ToolCache {
+    ttl: Duration,
+    capacity: usize,
+    overrides: HashMap<String, bool>,
+    state: Arc<Mutex<State>>,
+}
+
+impl Default for ToolCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ToolCache {
+    pub fn new() -> Self {
+        Self {
+            ttl: DEFAULT_CACHE_TTL,
+            capacity: DEFAULT_CACHE_CAPACITY,
+            overrides: HashMap::new(),
+            state: Default::default(),
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets how many results are kept at most.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Caches `tool`, or never caches it, regardless of its annotations.
+    pub fn with_tool(mut self, tool: impl Into<String>, cached: bool) -> Self {
+        self.overrides.insert(tool.into(), cached);
+        self
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let state = self.state.lock().unwrap();
+        CacheStats {
+            entries: state.entries.len(),
+            ..state.stats
+        }
+    }
+
+    /// Drops every cached result.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.clear();
+        state.recency.clear();
+    }
+
+    fn is_cached(&self, call: &ToolCall) -> bool {
+        match self.overrides.get(&call.name) {
+            Some(cached) => *cached,
+            None => call
+                .tool
+                .as_ref()
+                .is_some_and(|tool| tool.is_read_only() || tool.is_idempotent()),
+        }
+    }
+
+    /// Scoped to the authenticated subject, or else to the session. Calls outside any session,
+    /// such as direct calls to a service, share one scope.
+    fn key(call: &ToolCall) -> String {
+        let ctx = &call.ctx;
+        let owner = match (&ctx.auth, &ctx.session_id) {
+            (Some(auth), _) => format!("sub:{}", auth.subject),
+            (None, Some(session_id)) => format!("session:{session_id}"),
+            (None, None) => String::new(),
+        };
+        let mut key = format!("{}\0{}\0", owner, call.name);
+        write_canonical(&call.arguments, &mut key);
+        key
+    }
+}
+
+/// Writes `value` as JSON with object keys sorted, so that equal arguments give equal keys.
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(value, out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+#[async_trait]
+impl ToolMiddleware for ToolCache {
+    async fn before_call(&self, call: &mut ToolCall) -> Result<Option<Vec<Content>>> {
+        if !self.is_cached(call) {
+            return Ok(None);
+        }
+        let mut state = self.state.lock().unwrap();
+        let content = state.get(&Self::key(call));
+        match content {
+            Some(_) => state.stats.hits += 1,
+            None => state.stats.misses += 1,
+        }
+        Ok(content)
+    }
+
+    async fn after_call(
+        &self,
+        call: &ToolCall,
+        result: Result<Vec<Content>>,
+    ) -> Result<Vec<Content>> {
+        if let Ok(content) = &result
+            && self.is_cached(call)
+        {
+            let mut state = self.state.lock().unwrap();
+            state.insert(Self::key(call), content.clone(), self.ttl, self.capacity);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        core::{Tool, ToolAnnotations},
+        error::Error,
+        service::{
+            context::RequestContext,
+            middleware::ToolPipeline,
+            registry::{Registry, RegistryService},
+            traits::Service,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_caches_read_only_successes() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let registry = Arc::new(Registry::new());
+        for (name, annotations) in [
+            ("search", Some(ToolAnnotations::read_only())),
+            ("write", None),
+        ] {
+            let mut tool = Tool::new(name, "", json!({ "type": "object" }));
+            tool.annotations = annotations;
+            let calls = calls.clone();
+            registry.add_tool(tool, move |args, _| {
+                let n = calls.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if args["fail"] == true {
+                        return Err(Error::System("failed".into()));
+                    }
+                    Ok(vec![Content::text(n.to_string())])
+                }
+            });
+        }
+        let cache = ToolCache::new().with_capacity(1);
+        let pipeline = ToolPipeline::new(Arc::new(RegistryService::new("cache", "", registry)))
+            .with_middleware(Arc::new(cache.clone()));
+        let call = |name: &'static str, args: Value| {
+            let pipeline = &pipeline;
+            async move {
+                pipeline
+                    .call_tool(name, args, RequestContext::default())
+                    .await
+                    .map(|content| content[0].as_text().unwrap().to_string())
+            }
+        };
+
+        let first = call("search", json!({ "q": "rust", "n": 1 }))
+            .await
+            .unwrap();
+        let again = call("search", json!({ "n": 1, "q": "rust" }))
+            .await
+            .unwrap();
+        assert_eq!(first, again);
+        assert_ne!(
+            call("write", json!({})).await.unwrap(),
+            call("write", json!({})).await.unwrap()
+        );
+        assert!(call("search", json!({ "fail": true })).await.is_err());
+        assert!(call("search", json!({ "fail": true })).await.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+
+        call("search", json!({ "q": "other" })).await.unwrap();
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 4,
+                evictions: 1,
+                entries: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_sessions_do_not_share_results() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let registry = Arc::new(Registry::new());
+        let mut tool = Tool::new("whoami", "", json!({ "type": "object" }));
+        tool.annotations = Some(ToolAnnotations::read_only());
+        let counter = calls.clone();
+        registry.add_tool(tool, move |_, ctx| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async move { Ok(vec![Content::text(ctx.session_id.unwrap_or_default())]) }
+        });
+        let pipeline = ToolPipeline::new(Arc::new(RegistryService::new("cache", "", registry)))
+            .with_middleware(Arc::new(ToolCache::new()));
+        let call = |session: &str| {
+            let ctx = RequestContext {
+                session_id: Some(session.to_string()),
+                ..Default::default()
+            };
+            let pipeline = &pipeline;
+            async move {
+                let content = pipeline.call_tool("whoami", json!({}), ctx).await.unwrap();
+                content[0].as_text().unwrap().to_string()
+            }
+        };
+
+        assert_eq!(call("s1").await, "s1");
+        assert_eq!(call("s2").await, "s2");
+        assert_eq!(call("s1").await, "s1");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
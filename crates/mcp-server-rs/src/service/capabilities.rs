@@ -1,12 +1,50 @@
-use crate::core::protocol::capabilities::{
-    PromptsCapability, ResourcesCapability, ServerCapabilities, ToolsCapability,
+use serde_json::json;
+
+use crate::{
+    core::protocol::capabilities::{
+        PromptsCapability, ResourcesCapability, ServerCapabilities, ToolsCapability,
+    },
+    service::traits::Service,
 };
 
+/// Derives capabilities from what `service` provides right now.
+///
+/// Tools, resources and prompts are advertised when the service lists any, resource templates
+/// count as resources, and `listChanged` is set when the service pushes notifications.
+/// `subscribe`, `logging` and `completions` follow the registered handlers.
+pub fn infer_capabilities<S: Service + ?Sized>(service: &S) -> ServerCapabilities {
+    let list_changed = service.subscribe_notifications().is_some();
+    let subscribe = service.subscription_handler().is_some();
+
+    let mut builder = CapabilitiesBuilder::new();
+    if !service.list_tools().is_empty() {
+        builder = builder.with_tools(list_changed);
+    }
+    if subscribe
+        || !service.list_resources().is_empty()
+        || !service.list_resource_templates().is_empty()
+    {
+        builder = builder.with_resources(subscribe, list_changed);
+    }
+    if !service.list_prompts().is_empty() {
+        builder = builder.with_prompts(list_changed);
+    }
+    if service.logging_handler().is_some() {
+        builder = builder.with_logging();
+    }
+    if service.completion_handler().is_some() {
+        builder = builder.with_completions();
+    }
+    builder.build()
+}
+
 /// Builder for configuring and constructing capabilities
 pub struct CapabilitiesBuilder {
     tools: Option<ToolsCapability>,
     prompts: Option<PromptsCapability>,
     resources: Option<ResourcesCapability>,
+    logging: bool,
+    completions: bool,
 }
 
 impl Default for CapabilitiesBuilder {
@@ -21,6 +59,8 @@ impl CapabilitiesBuilder {
             tools: None,
             prompts: None,
             resources: None,
+            logging: false,
+            completions: false,
         }
     }
 
@@ -49,13 +89,92 @@ impl CapabilitiesBuilder {
         self
     }
 
-    /// Build the router with automatic capability inference
+    /// Enable the `logging/setLevel` request
+    pub fn with_logging(mut self) -> Self {
+        self.logging = true;
+        self
+    }
+
+    /// Enable the `completion/complete` request
+    pub fn with_completions(mut self) -> Self {
+        self.completions = true;
+        self
+    }
+
+    /// Build the capabilities exactly as configured; see [`infer_capabilities`] to derive them
     pub fn build(self) -> ServerCapabilities {
-        // Create capabilities based on what's configured
         ServerCapabilities {
             tools: self.tools,
             prompts: self.prompts,
             resources: self.resources,
+            logging: self.logging.then(|| json!({})),
+            completions: self.completions.then(|| json!({})),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        core::{Tool, content::Content, protocol::logging::LoggingLevel},
+        error::Result,
+        service::{
+            context::RequestContext,
+            impls::{chart::ChartRouter, counter::CounterRouter},
+            traits::LoggingHandler,
+        },
+    };
+
+    struct Logger;
+
+    #[async_trait]
+    impl Service for Logger {
+        fn name(&self) -> String {
+            "logger".into()
+        }
+
+        fn instructions(&self) -> String {
+            String::new()
+        }
+
+        fn list_tools(&self) -> Vec<Tool> {
+            vec![]
+        }
+
+        async fn call_tool(&self, _: &str, _: Value, _: RequestContext) -> Result<Vec<Content>> {
+            Ok(vec![])
+        }
+
+        fn logging_handler(&self) -> Option<&dyn LoggingHandler> {
+            Some(self)
+        }
+    }
+
+    #[async_trait]
+    impl LoggingHandler for Logger {
+        async fn set_level(&self, _: LoggingLevel, _: RequestContext) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_capabilities_follow_what_services_provide() {
+        let chart = ChartRouter::new().capabilities();
+        assert!(chart.tools.is_some());
+        assert!(chart.resources.is_none() && chart.prompts.is_none());
+
+        let counter = CounterRouter::new().capabilities();
+        assert!(
+            counter.tools.is_some() && counter.resources.is_some() && counter.prompts.is_some()
+        );
+        assert_eq!(counter.resources.unwrap().subscribe, Some(false));
+
+        let logger = Logger.capabilities();
+        assert_eq!(logger.logging, Some(json!({})));
+        assert!(logger.tools.is_none() && logger.completions.is_none());
+    }
+}
@@ -0,0 +1,528 @@
+//! Several services behind one server, with their tools and prompts namespaced by prefix.
+
+use std::{
+    collections::HashSet,
+    sync::{Arc, OnceLock},
+};
+
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+use crate::{
+    core::{
+        Resource, ResourceTemplate, Tool,
+        content::Content,
+        prompt::Prompt,
+        protocol::{
+            capabilities::{
+                PromptsCapability, ResourcesCapability, ServerCapabilities, ToolsCapability,
+            },
+            completion::{Completion, CompletionArgument, CompletionReference},
+            logging::LoggingLevel,
+            message::JsonRpcNotification,
+        },
+    },
+    error::{Error, Result},
+    service::{
+        context::RequestContext,
+        traits::{CompletionHandler, LoggingHandler, Service, SubscriptionHandler},
+    },
+};
+
+/// Separates a mount prefix from the child's own name, as in `chart.generate_chart`.
+pub const DEFAULT_SEPARATOR: &str = ".";
+
+/// Which child keeps a name that more than one of them exposes.
+///
+/// Collisions only happen when prefixes overlap, e.g. two children mounted without a prefix.
+/// The child that loses the name is hidden for it, both from lists and from calls.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CollisionRule {
+    /// The child mounted first wins.
+    #[default]
+    FirstWins,
+    /// The child mounted last wins.
+    LastWins,
+}
+
+struct Mount {
+    prefix: String,
+    service: Arc<dyn Service>,
+}
+
+/// A [`Service`] that serves any number of child services.
+///
+/// Tools and prompts of a child mounted under `chart` are exposed as `chart.<name>`; with an
+/// empty prefix they keep their names. Resources keep their URIs, since those already identify
+/// them, and reads go to the child that lists the URI. Capabilities and instructions are merged
+/// from the children.
+pub struct CompositeService {
+    name: String,
+    separator: String,
+    collision_rule: CollisionRule,
+    mounts: Vec<Mount>,
+    notifier: OnceLock<Option<broadcast::Sender<JsonRpcNotification>>>,
+}
+
+impl CompositeService {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            separator: DEFAULT_SEPARATOR.to_string(),
+            collision_rule: CollisionRule::default(),
+            mounts: Vec::new(),
+            notifier: OnceLock::new(),
+        }
+    }
+
+    /// Mounts `service` so that its tools and prompts appear as `{prefix}{separator}{name}`.
+    pub fn mount(mut self, prefix: impl Into<String>, service: impl Service + 'static) -> Self {
+        self.mounts.push(Mount {
+            prefix: prefix.into(),
+            service: Arc::new(service),
+        });
+        self
+    }
+
+    pub fn with_separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    pub fn with_collision_rule(mut self, rule: CollisionRule) -> Self {
+        self.collision_rule = rule;
+        self
+    }
+
+    /// The children in the order in which they claim names.
+    fn by_priority(&self) -> Vec<&Mount> {
+        match self.collision_rule {
+            CollisionRule::FirstWins => self.mounts.iter().collect(),
+            CollisionRule::LastWins => self.mounts.iter().rev().collect(),
+        }
+    }
+
+    fn qualify(&self, mount: &Mount, name: &str) -> String {
+        if mount.prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}{}{}", mount.prefix, self.separator, name)
+        }
+    }
+
+    fn unqualify<'n>(&self, mount: &Mount, name: &'n str) -> Option<&'n str> {
+        if mount.prefix.is_empty() {
+            return Some(name);
+        }
+        name.strip_prefix(mount.prefix.as_str())?
+            .strip_prefix(self.separator.as_str())
+    }
+
+    /// Merges one kind of item from every child, renaming with the mount prefix if `qualify` is
+    /// set and dropping names already claimed by a child of higher priority. Items stay in mount
+    /// order.
+    fn merge<T>(
+        &self,
+        list: impl Fn(&dyn Service) -> Vec<T>,
+        key: impl Fn(&mut T) -> &mut String,
+        qualify: bool,
+    ) -> Vec<T> {
+        let mut merged: Vec<Vec<T>> = self.mounts.iter().map(|_| Vec::new()).collect();
+        let mut claimed = HashSet::new();
+        let order: Vec<usize> = match self.collision_rule {
+            CollisionRule::FirstWins => (0 .. self.mounts.len()).collect(),
+            CollisionRule::LastWins => (0 .. self.mounts.len()).rev().collect(),
+        };
+        for index in order {
+            let mount = &self.mounts[index];
+            let mut items = list(&*mount.service);
+            items.retain_mut(|item| {
+                let key = key(item);
+                if qualify {
+                    *key = self.qualify(mount, key);
+                }
+                claimed.insert(key.clone())
+            });
+            merged[index] = items;
+        }
+        merged.into_iter().flatten().collect()
+    }
+
+    /// The child that owns the qualified `name` and the name it knows it by.
+    fn route<'s, 'n, T>(
+        &'s self,
+        name: &'n str,
+        list: impl Fn(&dyn Service) -> Vec<T>,
+        key: impl Fn(&T) -> &str,
+    ) -> Option<(&'s Mount, &'n str)> {
+        self.by_priority().into_iter().find_map(|mount| {
+            let local = self.unqualify(mount, name)?;
+            list(&*mount.service)
+                .iter()
+                .any(|item| key(item) == local)
+                .then_some((mount, local))
+        })
+    }
+
+    fn subscriptions_for(&self, uri: &str) -> Result<&dyn SubscriptionHandler> {
+        self.route(uri, |s| s.list_resources(), |r| &r.uri)
+            .and_then(|(mount, _)| mount.service.subscription_handler())
+            .or_else(|| {
+                self.by_priority()
+                    .into_iter()
+                    .find_map(|mount| mount.service.subscription_handler())
+            })
+            .ok_or_else(|| Error::ResourceNotFound(uri.to_string()))
+    }
+
+    fn forward_notifications(&self) -> Option<broadcast::Sender<JsonRpcNotification>> {
+        let receivers: Vec<_> = self
+            .mounts
+            .iter()
+            .filter_map(|m| m.service.subscribe_notifications())
+            .collect();
+        if receivers.is_empty() {
+            return None;
+        }
+        let (notifier, _) = broadcast::channel(64);
+        for mut receiver in receivers {
+            let notifier = notifier.clone();
+            tokio::spawn(async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(notification) => {
+                            let _ = notifier.send(notification);
+                        }
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+        }
+        Some(notifier)
+    }
+}
+
+#[async_trait]
+impl Service for CompositeService {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn instructions(&self) -> String {
+        self.mounts
+            .iter()
+            .filter_map(|mount| {
+                let instructions = mount.service.instructions();
+                if instructions.is_empty() {
+                    return None;
+                }
+                Some(if mount.prefix.is_empty() {
+                    instructions
+                } else {
+                    format!(
+                        "Tools and prompts prefixed with `{}{}`: {}",
+                        mount.prefix, self.separator, instructions
+                    )
+                })
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    fn capabilities(&self) -> ServerCapabilities {
+        let mut merged = ServerCapabilities::default();
+        let or = |a: Option<bool>, b: Option<bool>| match (a, b) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(false) || b.unwrap_or(false)),
+        };
+        for mount in &self.mounts {
+            let child = mount.service.capabilities();
+            if let Some(tools) = child.tools {
+                let current = merged
+                    .tools
+                    .get_or_insert(ToolsCapability { list_changed: None });
+                current.list_changed = or(current.list_changed, tools.list_changed);
+            }
+            if let Some(prompts) = child.prompts {
+                let current = merged
+                    .prompts
+                    .get_or_insert(PromptsCapability { list_changed: None });
+                current.list_changed = or(current.list_changed, prompts.list_changed);
+            }
+            if let Some(resources) = child.resources {
+                let current = merged.resources.get_or_insert(ResourcesCapability {
+                    subscribe: None,
+                    list_changed: None,
+                });
+                current.subscribe = or(current.subscribe, resources.subscribe);
+                current.list_changed = or(current.list_changed, resources.list_changed);
+            }
+            merged.logging = merged.logging.or(child.logging);
+            merged.completions = merged.completions.or(child.completions);
+        }
+        merged
+    }
+
+    fn list_tools(&self) -> Vec<Tool> {
+        self.merge(|s| s.list_tools(), |t| &mut t.name, true)
+    }
+
+    async fn call_tool(
+        &self,
+        tool_name: &str,
+        arguments: Value,
+        ctx: RequestContext,
+    ) -> Result<Vec<Content>> {
+        let (mount, local) = self
+            .route(tool_name, |s| s.list_tools(), |t| &t.name)
+            .ok_or_else(|| Error::System(format!("Tool {} not found", tool_name)))?;
+        mount.service.call_tool(local, arguments, ctx).await
+    }
+
+    fn list_resources(&self) -> Vec<Resource> {
+        self.merge(|s| s.list_resources(), |r| &mut r.uri, false)
+    }
+
+    fn list_resource_templates(&self) -> Vec<ResourceTemplate> {
+        self.merge(
+            |s| s.list_resource_templates(),
+            |t| &mut t.uri_template,
+            false,
+        )
+    }
+
+    async fn read_resource(&self, uri: &str, ctx: RequestContext) -> Result<String> {
+        if let Some((mount, _)) = self.route(uri, |s| s.list_resources(), |r| &r.uri) {
+            return mount.service.read_resource(uri, ctx).await;
+        }
+        // URIs built from templates are not listed, so ask each child that serves resources
+        // until one knows it. Any other failure is the answer.
+        for mount in self.by_priority() {
+            if mount.service.capabilities().resources.is_none() {
+                continue;
+            }
+            match mount.service.read_resource(uri, ctx.clone()).await {
+                Err(Error::ResourceNotFound(_)) => continue,
+                result => return result,
+            }
+        }
+        Err(Error::ResourceNotFound(uri.to_string()))
+    }
+
+    fn list_prompts(&self) -> Vec<Prompt> {
+        self.merge(|s| s.list_prompts(), |p| &mut p.name, true)
+    }
+
+    async fn get_prompt(&self, prompt_name: &str, ctx: RequestContext) -> Result<String> {
+        let (mount, local) = self
+            .route(prompt_name, |s| s.list_prompts(), |p| &p.name)
+            .ok_or_else(|| {
+                Error::InvalidParameters(format!("Prompt '{}' not found", prompt_name))
+            })?;
+        mount.service.get_prompt(local, ctx).await
+    }
+
+    fn logging_handler(&self) -> Option<&dyn LoggingHandler> {
+        let any = self
+            .mounts
+            .iter()
+            .any(|m| m.service.logging_handler().is_some());
+        any.then_some(self as &dyn LoggingHandler)
+    }
+
+    fn completion_handler(&self) -> Option<&dyn CompletionHandler> {
+        let any = self
+            .mounts
+            .iter()
+            .any(|m| m.service.completion_handler().is_some());
+        any.then_some(self as &dyn CompletionHandler)
+    }
+
+    fn subscription_handler(&self) -> Option<&dyn SubscriptionHandler> {
+        let any = self
+            .mounts
+            .iter()
+            .any(|m| m.service.subscription_handler().is_some());
+        any.then_some(self as &dyn SubscriptionHandler)
+    }
+
+    fn subscribe_notifications(&self) -> Option<broadcast::Receiver<JsonRpcNotification>> {
+        self.notifier
+            .get_or_init(|| self.forward_notifications())
+            .as_ref()
+            .map(broadcast::Sender::subscribe)
+    }
+}
+
+/// Sets the level on every child that logs.
+#[async_trait]
+impl LoggingHandler for CompositeService {
+    async fn set_level(&self, level: LoggingLevel, ctx: RequestContext) -> Result<()> {
+        for mount in &self.mounts {
+            if let Some(handler) = mount.service.logging_handler() {
+                handler.set_level(level, ctx.clone()).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sends prompt completions to the child that owns the prompt, and resource completions to the
+/// child that lists the template.
+#[async_trait]
+impl CompletionHandler for CompositeService {
+    async fn complete(
+        &self,
+        reference: CompletionReference,
+        argument: CompletionArgument,
+        ctx: RequestContext,
+    ) -> Result<Completion> {
+        let (mount, reference) = match &reference {
+            CompletionReference::Prompt { name } => {
+                let (mount, local) = self
+                    .route(name, |s| s.list_prompts(), |p| &p.name)
+                    .ok_or_else(|| {
+                        Error::InvalidParameters(format!("Prompt '{}' not found", name))
+                    })?;
+                let local = CompletionReference::Prompt {
+                    name: local.to_string(),
+                };
+                (mount, local)
+            }
+            CompletionReference::Resource { uri } => {
+                let (mount, _) = self
+                    .route(uri, |s| s.list_resource_templates(), |t| &t.uri_template)
+                    .ok_or_else(|| Error::ResourceNotFound(uri.clone()))?;
+                (mount, reference.clone())
+            }
+        };
+        match mount.service.completion_handler() {
+            Some(handler) => handler.complete(reference, argument, ctx).await,
+            None => Ok(Completion::default()),
+        }
+    }
+}
+
+/// Sends subscriptions to the child that lists the resource, or else to the first child that
+/// takes subscriptions.
+#[async_trait]
+impl SubscriptionHandler for CompositeService {
+    async fn subscribe(&self, uri: &str, ctx: RequestContext) -> Result<()> {
+        self.subscriptions_for(uri)?.subscribe(uri, ctx).await
+    }
+
+    async fn unsubscribe(&self, uri: &str, ctx: RequestContext) -> Result<()> {
+        self.subscriptions_for(uri)?.unsubscribe(uri, ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::service::{
+        capabilities::CapabilitiesBuilder,
+        impls::counter::CounterRouter,
+        registry::{Registry, RegistryService},
+    };
+
+    fn echo_service(reply: &'static str) -> RegistryService {
+        let registry = Arc::new(Registry::new());
+        registry.add_tool(
+            Tool::new("echo", "", json!({ "type": "object" })),
+            move |_, _| async move { Ok(vec![Content::text(reply)]) },
+        );
+        RegistryService::new(reply, "", registry)
+    }
+
+    async fn call(service: &CompositeService, name: &str) -> Result<String> {
+        let content = service
+            .call_tool(name, json!({}), RequestContext::default())
+            .await?;
+        Ok(content[0].as_text().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_routes_by_prefix_and_resolves_collisions() {
+        let composite = CompositeService::new("all")
+            .mount("counter", CounterRouter::new())
+            .mount("", echo_service("first"))
+            .mount("", echo_service("second"));
+
+        let names: Vec<_> = composite.list_tools().into_iter().map(|t| t.name).collect();
+        assert!(names.contains(&"counter.increment".to_string()));
+        assert_eq!(names.iter().filter(|n| *n == "echo").count(), 1);
+        assert_eq!(call(&composite, "counter.increment").await.unwrap(), "1");
+        assert_eq!(call(&composite, "echo").await.unwrap(), "first");
+        assert!(call(&composite, "increment").await.is_err());
+        assert!(composite.capabilities().tools.is_some());
+        assert!(
+            composite
+                .instructions()
+                .starts_with("Tools and prompts prefixed with `counter.`")
+        );
+
+        let composite = composite.with_collision_rule(CollisionRule::LastWins);
+        assert_eq!(call(&composite, "echo").await.unwrap(), "second");
+    }
+
+    /// Serves resources but cannot read any right now.
+    struct Unavailable;
+
+    #[async_trait]
+    impl Service for Unavailable {
+        fn name(&self) -> String {
+            "unavailable".into()
+        }
+
+        fn instructions(&self) -> String {
+            String::new()
+        }
+
+        fn capabilities(&self) -> ServerCapabilities {
+            CapabilitiesBuilder::new()
+                .with_resources(false, false)
+                .build()
+        }
+
+        fn list_tools(&self) -> Vec<Tool> {
+            Vec::new()
+        }
+
+        async fn call_tool(
+            &self,
+            name: &str,
+            _arguments: Value,
+            _ctx: RequestContext,
+        ) -> Result<Vec<Content>> {
+            Err(Error::System(format!("Tool {} not found", name)))
+        }
+
+        async fn read_resource(&self, _uri: &str, _ctx: RequestContext) -> Result<String> {
+            Err(Error::System("Backend unavailable".into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_resource_fallback() {
+        let read = |composite: CompositeService| async move {
+            composite
+                .read_resource("db://rows/1", RequestContext::default())
+                .await
+        };
+
+        let composite = CompositeService::new("all").mount("counter", CounterRouter::new());
+        assert!(matches!(
+            read(composite).await,
+            Err(Error::ResourceNotFound(_))
+        ));
+
+        let composite = CompositeService::new("all")
+            .mount("echo", echo_service("echo"))
+            .mount("db", Unavailable);
+        assert!(matches!(read(composite).await, Err(Error::System(_))));
+    }
+}
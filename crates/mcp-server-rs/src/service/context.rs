@@ -0,0 +1,287 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use serde_json::{Value, json};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    core::protocol::{
+        capabilities::ClientCapabilities,
+        error::ErrorData,
+        message::{JsonRpcMessage, JsonRpcNotification, JsonRpcRequest},
+        result::Implementation,
+    },
+    error::{Error, Result},
+};
+
+/// The authenticated caller, as established by the transport.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuthIdentity {
+    pub subject: String,
+    pub scopes: Vec<String>,
+    /// Any further claims the authenticator exposes, e.g. the decoded token payload.
+    pub claims: Value,
+}
+
+impl AuthIdentity {
+    pub fn new(subject: impl Into<String>) -> Self {
+        Self {
+            subject: subject.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_scopes<I, S>(mut self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_claims(mut self, claims: Value) -> Self {
+        self.claims = claims;
+        self
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
+
+/// How long [`Peer::request`] waits for the client's answer.
+pub const DEFAULT_PEER_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+type PendingRequests = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value>>>>>;
+
+/// Forgets a pending request however the wait for it ends, including by being dropped.
+struct PendingGuard<'a> {
+    pending: &'a PendingRequests,
+    id: u64,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.pending.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Handle for sending requests and notifications to the client of a session.
+#[derive(Clone)]
+pub struct Peer {
+    outbound: mpsc::UnboundedSender<JsonRpcMessage>,
+    pending: PendingRequests,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Peer {
+    pub(crate) fn new(outbound: mpsc::UnboundedSender<JsonRpcMessage>) -> Self {
+        Self {
+            outbound,
+            pending: Default::default(),
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    /// A peer that is not attached to any session. Every send fails with `ChannelClosed`.
+    pub fn detached() -> Self {
+        let (outbound, _) = mpsc::unbounded_channel();
+        Self::new(outbound)
+    }
+
+    /// Sends a request to the client (e.g. `sampling/createMessage` or `roots/list`) and waits
+    /// for its result, up to [`DEFAULT_PEER_REQUEST_TIMEOUT`].
+    pub async fn request(&self, method: &str, params: Option<Value>) -> Result<Value> {
+        self.request_with_timeout(method, params, DEFAULT_PEER_REQUEST_TIMEOUT)
+            .await
+    }
+
+    /// Like [`Peer::request`], but gives up after `timeout` with [`Error::RequestTimeout`] and
+    /// tells the client with `notifications/cancelled`.
+    pub async fn request_with_timeout(
+        &self,
+        method: &str,
+        params: Option<Value>,
+        timeout: Duration,
+    ) -> Result<Value> {
+        let id = self.next_id();
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, tx);
+        let _pending = PendingGuard {
+            pending: &self.pending,
+            id,
+        };
+
+        let request = JsonRpcRequest::new(Some(id), method, params);
+        self.send(JsonRpcMessage::Request(request))?;
+
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(result) => result.map_err(|_| Error::ChannelClosed)?,
+            Err(_) => {
+                let reason = json!({ "requestId": id, "reason": "Timed out" });
+                // The client may be gone, which is likely why it did not answer.
+                let _ = self.notify("notifications/cancelled", Some(reason));
+                Err(Error::RequestTimeout {
+                    method: method.to_string(),
+                    timeout,
+                })
+            }
+        }
+    }
+
+    pub fn notify(&self, method: &str, params: Option<Value>) -> Result<()> {
+        self.send(JsonRpcMessage::Notification(JsonRpcNotification::new(
+            method, params,
+        )))
+    }
+
+    pub(crate) fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub(crate) fn send(&self, msg: JsonRpcMessage) -> Result<()> {
+        self.outbound.send(msg).map_err(|_| Error::ChannelClosed)
+    }
+
+    /// Fails every request still waiting for the client with `ChannelClosed`.
+    pub(crate) fn fail_pending(&self) {
+        self.pending.lock().unwrap().clear();
+    }
+
+    /// Hands the client's answer to the matching [`Peer::request`]. Returns `false` when no
+    /// request with this id is waiting.
+    pub(crate) fn resolve(
+        &self,
+        id: Option<u64>,
+        result: Option<Value>,
+        error: Option<ErrorData>,
+    ) -> bool {
+        let Some(tx) = id.and_then(|id| self.pending.lock().unwrap().remove(&id)) else {
+            return false;
+        };
+        let result = match error {
+            Some(error) => Err(Error::Rpc {
+                code: error.code,
+                message: error.message,
+                data: error.data,
+            }),
+            None => Ok(result.unwrap_or(Value::Null)),
+        };
+        // The requester may have given up waiting, which is fine.
+        let _ = tx.send(result);
+        true
+    }
+}
+
+/// Reports progress for a request whose caller supplied `_meta.progressToken`.
+#[derive(Clone)]
+pub struct ProgressReporter {
+    token: Option<Value>,
+    peer: Peer,
+}
+
+impl ProgressReporter {
+    pub(crate) fn new(token: Option<Value>, peer: Peer) -> Self {
+        Self { token, peer }
+    }
+
+    /// Whether the client asked for progress notifications.
+    pub fn is_enabled(&self) -> bool {
+        self.token.is_some()
+    }
+
+    /// Sends `notifications/progress`. Does nothing when the client did not ask for progress.
+    pub fn report(&self, progress: f64, total: Option<f64>, message: Option<&str>) -> Result<()> {
+        let Some(token) = &self.token else {
+            return Ok(());
+        };
+
+        let mut params = json!({ "progressToken": token, "progress": progress });
+        if let Some(total) = total {
+            params["total"] = json!(total);
+        }
+        if let Some(message) = message {
+            params["message"] = json!(message);
+        }
+        self.peer.notify("notifications/progress", Some(params))
+    }
+}
+
+/// Everything a handler may need to know about the request it is serving.
+#[derive(Clone)]
+pub struct RequestContext {
+    pub request_id: Option<u64>,
+    pub session_id: Option<String>,
+    /// Protocol version agreed on during `initialize`.
+    pub protocol_version: Option<String>,
+    pub client_capabilities: Option<ClientCapabilities>,
+    pub client_info: Option<Implementation>,
+    pub auth: Option<AuthIdentity>,
+    /// Cancelled when the client sends `notifications/cancelled` for this request or the session
+    /// ends. Long-running handlers should check it.
+    pub cancellation: CancellationToken,
+    pub progress: ProgressReporter,
+    pub peer: Peer,
+}
+
+impl Default for RequestContext {
+    /// A context that is not attached to any session, for calling a service directly.
+    fn default() -> Self {
+        let peer = Peer::detached();
+        Self {
+            request_id: None,
+            session_id: None,
+            protocol_version: None,
+            client_capabilities: None,
+            client_info: None,
+            auth: None,
+            cancellation: CancellationToken::new(),
+            progress: ProgressReporter::new(None, peer.clone()),
+            peer,
+        }
+    }
+}
+
+impl RequestContext {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_forgets_abandoned_waits() {
+        let (outbound, mut rx) = mpsc::unbounded_channel();
+        let peer = Peer::new(outbound);
+
+        let err = peer
+            .request_with_timeout("roots/list", None, Duration::from_millis(10))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::RequestTimeout { .. }));
+        assert!(matches!(rx.recv().await, Some(JsonRpcMessage::Request(_))));
+        match rx.recv().await {
+            Some(JsonRpcMessage::Notification(n)) => {
+                assert_eq!(n.method, "notifications/cancelled")
+            }
+            other => panic!("Expected cancellation, got {other:?}"),
+        }
+
+        let request = peer.request("roots/list", None);
+        tokio::time::timeout(Duration::from_millis(10), request)
+            .await
+            .unwrap_err();
+        assert!(peer.pending.lock().unwrap().is_empty());
+    }
+}
@@ -7,15 +7,23 @@ use crate::{
         content::Content,
         prompt::{PromptMessage, PromptMessageRole},
         protocol::{
+            completion::{CompletionArgument, CompletionReference},
+            constants::PROTOCOL_VERSION,
+            logging::LoggingLevel,
             message::{JsonRpcRequest, JsonRpcResponse},
             result::{
-                CallToolResult, GetPromptResult, Implementation, InitializeResult,
-                ListPromptsResult, ListResourcesResult, ListToolsResult, ReadResourceResult,
+                CallToolResult, CompleteResult, EmptyResult, GetPromptResult, Implementation,
+                InitializeResult, ListPromptsResult, ListResourceTemplatesResult,
+                ListResourcesResult, ListToolsResult, ReadResourceResult,
             },
         },
     },
     error::{Error, Result},
-    service::traits::Service,
+    service::{
+        context::RequestContext,
+        pagination::{ListKind, Pagination},
+        traits::Service,
+    },
 };
 
 #[async_trait]
@@ -27,7 +35,7 @@ pub trait ServiceExt: Service {
 
     async fn handle_initialize(&self, req: JsonRpcRequest) -> Result<JsonRpcResponse> {
         let result = InitializeResult {
-            protocol_version: "2024-11-05".to_string(),
+            protocol_version: PROTOCOL_VERSION.to_string(),
             capabilities: self.capabilities(),
             server_info: Implementation {
                 name: self.name(),
@@ -45,12 +53,29 @@ pub trait ServiceExt: Service {
         Ok(response)
     }
 
-    async fn handle_tools_list(&self, req: JsonRpcRequest) -> Result<JsonRpcResponse> {
-        let tools = self.list_tools();
+    async fn handle_ping(&self, req: JsonRpcRequest) -> Result<JsonRpcResponse> {
+        let mut response = self.create_response(req.id);
+        response.result = Some(
+            serde_json::to_value(EmptyResult {})
+                .map_err(|e| Error::System(format!("JSON serialization error: {}", e)))?,
+        );
+
+        Ok(response)
+    }
+
+    async fn handle_tools_list(
+        &self,
+        req: JsonRpcRequest,
+        pagination: &Pagination,
+    ) -> Result<JsonRpcResponse> {
+        let request = pagination.page_request(ListKind::Tools, req.params.as_ref())?;
+        let page = self.list_tools_page(request).await?;
 
         let result = ListToolsResult {
-            tools,
-            next_cursor: None,
+            tools: page.items,
+            next_cursor: page
+                .next_cursor
+                .map(|position| pagination.encode(ListKind::Tools, &position)),
         };
         let mut response = self.create_response(req.id);
         response.result = Some(
@@ -61,7 +86,11 @@ pub trait ServiceExt: Service {
         Ok(response)
     }
 
-    async fn handle_tools_call(&self, req: JsonRpcRequest) -> Result<JsonRpcResponse> {
+    async fn handle_tools_call(
+        &self,
+        req: JsonRpcRequest,
+        ctx: RequestContext,
+    ) -> Result<JsonRpcResponse> {
         let params = req
             .params
             .ok_or_else(|| Error::InvalidParameters("Missing parameters".into()))?;
@@ -73,11 +102,13 @@ pub trait ServiceExt: Service {
 
         let arguments = params.get("arguments").cloned().unwrap_or(Value::Null);
 
-        let result = match self.call_tool(name, arguments).await {
+        let result = match self.call_tool(name, arguments, ctx).await {
             Ok(result) => CallToolResult {
                 content: result,
                 is_error: None,
             },
+            // Explicit JSON-RPC errors are protocol errors rather than tool failures.
+            Err(err @ Error::Rpc { .. }) => return Err(err),
             Err(err) => CallToolResult {
                 content: vec![Content::text(err.to_string())],
                 is_error: Some(true),
@@ -93,12 +124,19 @@ pub trait ServiceExt: Service {
         Ok(response)
     }
 
-    async fn handle_resources_list(&self, req: JsonRpcRequest) -> Result<JsonRpcResponse> {
-        let resources = self.list_resources();
+    async fn handle_resources_list(
+        &self,
+        req: JsonRpcRequest,
+        pagination: &Pagination,
+    ) -> Result<JsonRpcResponse> {
+        let request = pagination.page_request(ListKind::Resources, req.params.as_ref())?;
+        let page = self.list_resources_page(request).await?;
 
         let result = ListResourcesResult {
-            resources,
-            next_cursor: None,
+            resources: page.items,
+            next_cursor: page
+                .next_cursor
+                .map(|position| pagination.encode(ListKind::Resources, &position)),
         };
         let mut response = self.create_response(req.id);
         response.result = Some(
@@ -109,7 +147,34 @@ pub trait ServiceExt: Service {
         Ok(response)
     }
 
-    async fn handle_resources_read(&self, req: JsonRpcRequest) -> Result<JsonRpcResponse> {
+    async fn handle_resource_templates_list(
+        &self,
+        req: JsonRpcRequest,
+        pagination: &Pagination,
+    ) -> Result<JsonRpcResponse> {
+        let request = pagination.page_request(ListKind::ResourceTemplates, req.params.as_ref())?;
+        let page = self.list_resource_templates_page(request).await?;
+
+        let result = ListResourceTemplatesResult {
+            resource_templates: page.items,
+            next_cursor: page
+                .next_cursor
+                .map(|position| pagination.encode(ListKind::ResourceTemplates, &position)),
+        };
+        let mut response = self.create_response(req.id);
+        response.result = Some(
+            serde_json::to_value(result)
+                .map_err(|e| Error::System(format!("JSON serialization error: {}", e)))?,
+        );
+
+        Ok(response)
+    }
+
+    async fn handle_resources_read(
+        &self,
+        req: JsonRpcRequest,
+        ctx: RequestContext,
+    ) -> Result<JsonRpcResponse> {
         let params = req
             .params
             .ok_or_else(|| Error::InvalidParameters("Missing parameters".into()))?;
@@ -119,7 +184,7 @@ pub trait ServiceExt: Service {
             .and_then(Value::as_str)
             .ok_or_else(|| Error::InvalidParameters("Missing resource URI".into()))?;
 
-        let contents = self.read_resource(uri).await.map_err(Error::from)?;
+        let contents = self.read_resource(uri, ctx).await.map_err(Error::from)?;
 
         let result = ReadResourceResult {
             contents: vec![ResourceContents::TextResourceContents {
@@ -138,10 +203,20 @@ pub trait ServiceExt: Service {
         Ok(response)
     }
 
-    async fn handle_prompts_list(&self, req: JsonRpcRequest) -> Result<JsonRpcResponse> {
-        let prompts = self.list_prompts();
-
-        let result = ListPromptsResult { prompts };
+    async fn handle_prompts_list(
+        &self,
+        req: JsonRpcRequest,
+        pagination: &Pagination,
+    ) -> Result<JsonRpcResponse> {
+        let request = pagination.page_request(ListKind::Prompts, req.params.as_ref())?;
+        let page = self.list_prompts_page(request).await?;
+
+        let result = ListPromptsResult {
+            prompts: page.items,
+            next_cursor: page
+                .next_cursor
+                .map(|position| pagination.encode(ListKind::Prompts, &position)),
+        };
 
         let mut response = self.create_response(req.id);
         response.result = Some(
@@ -152,7 +227,11 @@ pub trait ServiceExt: Service {
         Ok(response)
     }
 
-    async fn handle_prompts_get(&self, req: JsonRpcRequest) -> Result<JsonRpcResponse> {
+    async fn handle_prompts_get(
+        &self,
+        req: JsonRpcRequest,
+        ctx: RequestContext,
+    ) -> Result<JsonRpcResponse> {
         let params = req
             .params
             .ok_or_else(|| Error::InvalidParameters("Missing parameters".into()))?;
@@ -174,7 +253,9 @@ pub trait ServiceExt: Service {
             .list_prompts()
             .into_iter()
             .find(|p| p.name == prompt_name)
-            .ok_or_else(|| Error::System(format!("Prompt '{}' not found", prompt_name)))?;
+            .ok_or_else(|| {
+                Error::InvalidParameters(format!("Prompt '{}' not found", prompt_name))
+            })?;
 
         // Validate required arguments
         if let Some(args) = &prompt.arguments {
@@ -196,10 +277,7 @@ pub trait ServiceExt: Service {
         }
 
         // Now get the prompt content
-        let description = self
-            .get_prompt(prompt_name)
-            .await
-            .map_err(|e| Error::System(e.to_string()))?;
+        let description = self.get_prompt(prompt_name, ctx).await?;
 
         // Validate prompt arguments for potential security issues from user text input
         // Checks:
@@ -268,6 +346,96 @@ pub trait ServiceExt: Service {
         Ok(response)
     }
 
+    async fn handle_logging_set_level(
+        &self,
+        req: JsonRpcRequest,
+        ctx: RequestContext,
+    ) -> Result<JsonRpcResponse> {
+        let handler = self
+            .logging_handler()
+            .ok_or_else(|| Error::MethodNotFound(req.method.clone()))?;
+        let level: LoggingLevel = req
+            .params
+            .as_ref()
+            .and_then(|params| params.get("level"))
+            .cloned()
+            .ok_or_else(|| Error::InvalidParameters("Missing logging level".into()))
+            .and_then(|level| {
+                serde_json::from_value(level)
+                    .map_err(|e| Error::InvalidParameters(format!("Invalid logging level: {}", e)))
+            })?;
+
+        handler.set_level(level, ctx).await?;
+        let mut response = self.create_response(req.id);
+        response.result = Some(
+            serde_json::to_value(EmptyResult {})
+                .map_err(|e| Error::System(format!("JSON serialization error: {}", e)))?,
+        );
+        Ok(response)
+    }
+
+    async fn handle_completion_complete(
+        &self,
+        req: JsonRpcRequest,
+        ctx: RequestContext,
+    ) -> Result<JsonRpcResponse> {
+        let handler = self
+            .completion_handler()
+            .ok_or_else(|| Error::MethodNotFound(req.method.clone()))?;
+        let params = req
+            .params
+            .ok_or_else(|| Error::InvalidParameters("Missing parameters".into()))?;
+        let field = |name: &str| {
+            params
+                .get(name)
+                .cloned()
+                .ok_or_else(|| Error::InvalidParameters(format!("Missing {}", name)))
+        };
+        let reference: CompletionReference = serde_json::from_value(field("ref")?)
+            .map_err(|e| Error::InvalidParameters(format!("Invalid ref: {}", e)))?;
+        let argument: CompletionArgument = serde_json::from_value(field("argument")?)
+            .map_err(|e| Error::InvalidParameters(format!("Invalid argument: {}", e)))?;
+
+        let completion = handler.complete(reference, argument, ctx).await?;
+
+        let mut response = self.create_response(req.id);
+        response.result = Some(
+            serde_json::to_value(CompleteResult { completion })
+                .map_err(|e| Error::System(format!("JSON serialization error: {}", e)))?,
+        );
+        Ok(response)
+    }
+
+    /// Handles `resources/subscribe`, or `resources/unsubscribe` if `subscribe` is false.
+    async fn handle_resources_subscribe(
+        &self,
+        req: JsonRpcRequest,
+        ctx: RequestContext,
+        subscribe: bool,
+    ) -> Result<JsonRpcResponse> {
+        let handler = self
+            .subscription_handler()
+            .ok_or_else(|| Error::MethodNotFound(req.method.clone()))?;
+        let uri = req
+            .params
+            .as_ref()
+            .and_then(|params| params.get("uri"))
+            .and_then(Value::as_str)
+            .ok_or_else(|| Error::InvalidParameters("Missing resource URI".into()))?;
+
+        if subscribe {
+            handler.subscribe(uri, ctx).await?;
+        } else {
+            handler.unsubscribe(uri, ctx).await?;
+        }
+        let mut response = self.create_response(req.id);
+        response.result = Some(
+            serde_json::to_value(EmptyResult {})
+                .map_err(|e| Error::System(format!("JSON serialization error: {}", e)))?,
+        );
+        Ok(response)
+    }
+
     // 可继续添加 handle_tools_call, handle_resources_read, handle_prompts_get 等
 }
 
@@ -3,12 +3,9 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::{
-    core::{
-        MimeType, Resource, Tool, content::Content, prompt::Prompt,
-        protocol::capabilities::ServerCapabilities,
-    },
+    core::{MimeType, Resource, Tool, content::Content, prompt::Prompt},
     error::{Error, Result},
-    service::{capabilities::CapabilitiesBuilder, traits::Service},
+    service::{context::RequestContext, traits::Service},
 };
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -44,14 +41,6 @@ impl Service for ChartRouter {
             .to_string()
     }
 
-    fn capabilities(&self) -> ServerCapabilities {
-        CapabilitiesBuilder::new()
-            .with_tools(false)
-            .with_resources(false, false)
-            .with_prompts(false)
-            .build()
-    }
-
     fn list_tools(&self) -> Vec<Tool> {
         vec![Tool::new(
             "generate_chart".to_string(),
@@ -69,7 +58,12 @@ impl Service for ChartRouter {
         )]
     }
 
-    async fn call_tool(&self, tool_name: &str, arguments: Value) -> Result<Vec<Content>> {
+    async fn call_tool(
+        &self,
+        tool_name: &str,
+        arguments: Value,
+        _ctx: RequestContext,
+    ) -> Result<Vec<Content>> {
         let tool_name = tool_name.to_string();
         match tool_name.as_str() {
             "generate_chart" => {
@@ -87,7 +81,7 @@ impl Service for ChartRouter {
         vec![]
     }
 
-    async fn read_resource(&self, _uri: &str) -> Result<String> {
+    async fn read_resource(&self, _uri: &str, _ctx: RequestContext) -> Result<String> {
         Err(Error::System(
             "No resources implemented for chart router.".into(),
         ))
@@ -119,7 +113,7 @@ impl Service for ChartRouter {
     //     })
     // }
 
-    async fn get_prompt(&self, _prompt_name: &str) -> Result<String> {
+    async fn get_prompt(&self, _prompt_name: &str, _ctx: RequestContext) -> Result<String> {
         Err(Error::System(
             "No prompts implemented for chart router.".into(),
         ))
@@ -9,10 +9,9 @@ use crate::{
         MimeType, Resource, Tool,
         content::Content,
         prompt::{Prompt, PromptArgument},
-        protocol::capabilities::ServerCapabilities,
     },
     error::{Error, Result},
-    service::{capabilities::CapabilitiesBuilder, traits::Service},
+    service::{context::RequestContext, traits::Service},
 };
 
 #[derive(Clone)]
@@ -62,14 +61,6 @@ impl Service for CounterRouter {
             .to_string()
     }
 
-    fn capabilities(&self) -> ServerCapabilities {
-        CapabilitiesBuilder::new()
-            .with_tools(false)
-            .with_resources(false, false)
-            .with_prompts(false)
-            .build()
-    }
-
     fn list_tools(&self) -> Vec<Tool> {
         vec![
             Tool::new(
@@ -102,7 +93,12 @@ impl Service for CounterRouter {
         ]
     }
 
-    async fn call_tool(&self, tool_name: &str, _arguments: Value) -> Result<Vec<Content>> {
+    async fn call_tool(
+        &self,
+        tool_name: &str,
+        _arguments: Value,
+        _ctx: RequestContext,
+    ) -> Result<Vec<Content>> {
         let this = self.clone();
         let tool_name = tool_name.to_string();
 
@@ -130,7 +126,7 @@ impl Service for CounterRouter {
         ]
     }
 
-    async fn read_resource(&self, uri: &str) -> Result<String> {
+    async fn read_resource(&self, uri: &str, _ctx: RequestContext) -> Result<String> {
         let uri = uri.to_string();
         match uri.as_str() {
             "str:////Users/to/some/path/" => {
@@ -141,7 +137,7 @@ impl Service for CounterRouter {
                 let memo = "Business Intelligence Memo\n\nAnalysis has revealed 5 key insights ...";
                 Ok(memo.to_string())
             }
-            _ => Err(Error::System(format!("Resource {} not found", uri))),
+            _ => Err(Error::ResourceNotFound(uri)),
         }
     }
 
@@ -157,7 +153,7 @@ impl Service for CounterRouter {
         )]
     }
 
-    async fn get_prompt(&self, prompt_name: &str) -> Result<String> {
+    async fn get_prompt(&self, prompt_name: &str, _ctx: RequestContext) -> Result<String> {
         let prompt_name = prompt_name.to_string();
         match prompt_name.as_str() {
             "example_prompt" => {
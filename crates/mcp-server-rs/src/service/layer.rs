@@ -0,0 +1,194 @@
+//! MCP request dispatch as a [`tower::Service`], so standard middleware can wrap it.
+
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use tower::util::BoxCloneService;
+
+use crate::{
+    core::protocol::message::{JsonRpcRequest, JsonRpcResponse},
+    error::{Error, Result},
+    service::{context::RequestContext, ext::ServiceExt, pagination::Pagination, traits::Service},
+};
+
+/// The error type of a layered stack. Layers such as `tower::timeout` add their own errors.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A type-erased stack of layers around an [`McpService`].
+pub type BoxMcpService = BoxCloneService<McpRequest, JsonRpcResponse, BoxError>;
+
+/// The methods [`McpService::dispatch`] handles. Keep in step with its `match`.
+pub const METHODS: &[&str] = &[
+    "initialize",
+    "ping",
+    "tools/list",
+    "tools/call",
+    "resources/list",
+    "resources/templates/list",
+    "resources/read",
+    "resources/subscribe",
+    "resources/unsubscribe",
+    "prompts/list",
+    "prompts/get",
+    "logging/setLevel",
+    "completion/complete",
+];
+
+/// A request together with the context it arrived in.
+pub struct McpRequest {
+    pub request: JsonRpcRequest,
+    pub ctx: RequestContext,
+}
+
+impl From<JsonRpcRequest> for McpRequest {
+    /// Wraps a request in a detached context, for use outside of a running session.
+    fn from(request: JsonRpcRequest) -> Self {
+        Self {
+            request,
+            ctx: RequestContext::default(),
+        }
+    }
+}
+
+/// Exposes a [`Service`] as a [`tower::Service`].
+///
+/// Protocol errors such as an unknown method or invalid parameters come back as `Err`, so that
+/// layers like retries can see them. [`Server`](crate::server::Server) turns them into JSON-RPC
+/// error responses.
+#[derive(Clone)]
+pub struct McpService {
+    router: Arc<dyn Service>,
+    pagination: Pagination,
+}
+
+impl McpService {
+    pub fn new(router: Arc<dyn Service>, pagination: Pagination) -> Self {
+        Self { router, pagination }
+    }
+
+    /// Routes a request to the matching handler.
+    pub async fn dispatch(
+        &self,
+        request: JsonRpcRequest,
+        ctx: RequestContext,
+    ) -> Result<JsonRpcResponse> {
+        let router = &*self.router;
+        let pagination = &self.pagination;
+        match request.method.as_str() {
+            "initialize" => router.handle_initialize(request).await,
+            "ping" => router.handle_ping(request).await,
+            "tools/list" => router.handle_tools_list(request, pagination).await,
+            "tools/call" => router.handle_tools_call(request, ctx).await,
+            "resources/list" => router.handle_resources_list(request, pagination).await,
+            "resources/templates/list" => {
+                router
+                    .handle_resource_templates_list(request, pagination)
+                    .await
+            }
+            "resources/read" => router.handle_resources_read(request, ctx).await,
+            "prompts/list" => router.handle_prompts_list(request, pagination).await,
+            "resources/subscribe" => router.handle_resources_subscribe(request, ctx, true).await,
+            "resources/unsubscribe" => router.handle_resources_subscribe(request, ctx, false).await,
+            "prompts/get" => router.handle_prompts_get(request, ctx).await,
+            "logging/setLevel" => router.handle_logging_set_level(request, ctx).await,
+            "completion/complete" => router.handle_completion_complete(request, ctx).await,
+            _ => Err(Error::MethodNotFound(request.method)),
+        }
+    }
+}
+
+impl tower::Service<McpRequest> for McpService {
+    type Response = JsonRpcResponse;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<JsonRpcResponse>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: McpRequest) -> Self::Future {
+        let service = self.clone();
+        Box::pin(async move { service.dispatch(req.request, req.ctx).await })
+    }
+}
+
+impl tower::Service<JsonRpcRequest> for McpService {
+    type Response = JsonRpcResponse;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<JsonRpcResponse>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        <Self as tower::Service<McpRequest>>::poll_ready(self, cx)
+    }
+
+    fn call(&mut self, request: JsonRpcRequest) -> Self::Future {
+        self.call(McpRequest::from(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::{
+        core::{
+            Tool,
+            content::Content,
+            protocol::message::{JsonRpcMessage, JsonRpcRequest},
+            utils::{QueueConfig, message_queue},
+        },
+        server::Server,
+        service::registry::{Registry, RegistryService},
+        transport::sse::SseTransport,
+    };
+
+    #[tokio::test]
+    async fn test_layers_wrap_dispatch() {
+        let registry = Arc::new(Registry::new());
+        registry.add_tool(
+            Tool::new("slow", "", json!({ "type": "object" })),
+            |_, _| async move {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok(vec![Content::text("done")])
+            },
+        );
+        let (layer_tx, mut layer_rx) = mpsc::unbounded_channel();
+        let server = Server::new(Box::new(RegistryService::new("slow", "", registry)))
+            .with_layer(tower::timeout::TimeoutLayer::new(Duration::from_millis(20)))
+            .with_layer(tower::layer::layer_fn(move |inner: BoxMcpService| {
+                let layer_tx = layer_tx.clone();
+                tower::service_fn(move |req: McpRequest| {
+                    let _ = layer_tx.send(req.request.method.clone());
+                    tower::ServiceExt::oneshot(inner.clone(), req)
+                })
+            }));
+
+        let (to_client_tx, mut to_client_rx) = message_queue(QueueConfig::default());
+        let (to_server_tx, to_server_rx) = message_queue(QueueConfig::default());
+        tokio::spawn(server.run(SseTransport::new(to_client_tx, to_server_rx)));
+
+        to_server_tx
+            .send(JsonRpcMessage::Request(JsonRpcRequest::new(
+                Some(1),
+                "tools/call",
+                Some(json!({ "name": "slow" })),
+            )))
+            .await
+            .unwrap();
+        match to_client_rx.recv().await.unwrap() {
+            JsonRpcMessage::Response(response) => {
+                assert_eq!(response.id, Some(1));
+                assert_eq!(response.error.unwrap().message, "request timed out");
+            }
+            other => panic!("Expected Response, got {other:?}"),
+        }
+        assert_eq!(layer_rx.recv().await.unwrap(), "tools/call");
+    }
+}
@@ -0,0 +1,220 @@
+//! Execution limits for tool calls, enforced outside of the services that implement the tools.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::{Instant, timeout_at},
+};
+
+use crate::{
+    core::content::Content,
+    error::{Error, Result},
+};
+
+/// Limits for one tool. Unset limits are not enforced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ToolLimit {
+    /// How long a call may take, including any time spent waiting for a free slot.
+    pub timeout: Option<Duration>,
+    /// How many calls may run at once, across every session sharing the [`ToolLimits`].
+    pub max_concurrency: Option<usize>,
+    /// How many calls may wait for a slot once `max_concurrency` are running. Further calls are
+    /// rejected right away.
+    pub max_queue: usize,
+}
+
+impl ToolLimit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = Some(max_concurrency.max(1));
+        self
+    }
+
+    pub fn with_max_queue(mut self, max_queue: usize) -> Self {
+        self.max_queue = max_queue;
+        self
+    }
+}
+
+/// Free slots and waiting calls of one tool.
+struct Slots {
+    semaphore: Arc<Semaphore>,
+    waiting: AtomicUsize,
+}
+
+/// Counts a call as waiting until it is dropped.
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Per-tool [`ToolLimit`]s, with a default for tools that have none of their own.
+///
+/// Clones share their slots, so giving clones of one `ToolLimits` to the server of every
+/// session caps concurrency across sessions. A call over a limit fails with
+/// [`Error::ToolTimeout`] or [`Error::ToolBusy`], which the client sees as a tool result with
+/// `is_error` set.
+#[derive(Clone, Default)]
+pub struct ToolLimits {
+    default: ToolLimit,
+    tools: HashMap<String, ToolLimit>,
+    slots: Arc<Mutex<HashMap<String, Arc<Slots>>>>,
+}
+
+impl ToolLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the limit for tools without one of their own.
+    pub fn with_default(mut self, limit: ToolLimit) -> Self {
+        self.default = limit;
+        self
+    }
+
+    pub fn with_tool(mut self, name: impl Into<String>, limit: ToolLimit) -> Self {
+        self.tools.insert(name.into(), limit);
+        self
+    }
+
+    /// The limit that applies to `tool`.
+    pub fn limit(&self, tool: &str) -> ToolLimit {
+        self.tools.get(tool).copied().unwrap_or(self.default)
+    }
+
+    /// Runs `call` for `tool` within its limit.
+    pub async fn run<F>(&self, tool: &str, call: F) -> Result<Vec<Content>>
+    where
+        F: Future<Output = Result<Vec<Content>>>,
+    {
+        let limit = self.limit(tool);
+        let deadline = limit.timeout.map(|timeout| Instant::now() + timeout);
+        let timed_out = || Error::ToolTimeout {
+            tool: tool.to_string(),
+            timeout: limit.timeout.unwrap_or_default(),
+        };
+
+        let _permit = match limit.max_concurrency {
+            Some(max_concurrency) => {
+                let acquire = self.acquire(tool, max_concurrency, limit.max_queue);
+                Some(match deadline {
+                    Some(deadline) => timeout_at(deadline, acquire)
+                        .await
+                        .map_err(|_| timed_out())??,
+                    None => acquire.await?,
+                })
+            }
+            None => None,
+        };
+
+        match deadline {
+            Some(deadline) => timeout_at(deadline, call).await.map_err(|_| timed_out())?,
+            None => call.await,
+        }
+    }
+
+    async fn acquire(
+        &self,
+        tool: &str,
+        max_concurrency: usize,
+        max_queue: usize,
+    ) -> Result<OwnedSemaphorePermit> {
+        let slots = self
+            .slots
+            .lock()
+            .unwrap()
+            .entry(tool.to_string())
+            .or_insert_with(|| {
+                Arc::new(Slots {
+                    semaphore: Arc::new(Semaphore::new(max_concurrency)),
+                    waiting: AtomicUsize::new(0),
+                })
+            })
+            .clone();
+
+        if let Ok(permit) = slots.semaphore.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+        slots
+            .waiting
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |waiting| {
+                (waiting < max_queue).then_some(waiting + 1)
+            })
+            .map_err(|_| Error::ToolBusy {
+                tool: tool.to_string(),
+                max_concurrency,
+                max_queue,
+            })?;
+        let _waiting = Waiting(&slots.waiting);
+
+        let permit = slots.semaphore.clone().acquire_owned().await;
+        permit.map_err(|_| Error::ChannelClosed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::oneshot;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_timeout_and_busy_limits() {
+        let limits = ToolLimits::new()
+            .with_default(ToolLimit::new().with_timeout(Duration::from_millis(20)))
+            .with_tool("single", ToolLimit::new().with_max_concurrency(1));
+
+        let slow = async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(vec![])
+        };
+        let err = limits.run("slow", slow).await.unwrap_err();
+        assert!(matches!(err, Error::ToolTimeout { ref tool, .. } if tool == "slow"));
+
+        let (started_tx, started_rx) = oneshot::channel();
+        let (release_tx, release_rx) = oneshot::channel::<()>();
+        let running = tokio::spawn({
+            let limits = limits.clone();
+            async move {
+                limits
+                    .run("single", async move {
+                        started_tx.send(()).unwrap();
+                        release_rx.await.unwrap();
+                        Ok(vec![Content::text("done")])
+                    })
+                    .await
+            }
+        });
+        started_rx.await.unwrap();
+
+        let err = limits
+            .run("single", async { Ok(vec![]) })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("is busy"), "{err}");
+
+        release_tx.send(()).unwrap();
+        assert_eq!(running.await.unwrap().unwrap().len(), 1);
+        assert!(limits.run("single", async { Ok(vec![]) }).await.is_ok());
+    }
+}
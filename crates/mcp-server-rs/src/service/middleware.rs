@@ -0,0 +1,343 @@
+//! Hooks around `tools/call`, for policy that should apply to every tool of a service.
+
+use std::{sync::Arc, time::Instant};
+
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+use crate::{
+    core::{
+        Resource, ResourceTemplate, Tool,
+        content::Content,
+        prompt::Prompt,
+        protocol::{capabilities::ServerCapabilities, message::JsonRpcNotification},
+    },
+    error::Result,
+    service::{
+        context::RequestContext,
+        limits::ToolLimits,
+        pagination::{Page, PageRequest},
+        traits::{CompletionHandler, LoggingHandler, Service, SubscriptionHandler},
+    },
+};
+
+/// A tool call on its way to the service.
+#[derive(Clone)]
+pub struct ToolCall {
+    pub name: String,
+    pub arguments: Value,
+    pub ctx: RequestContext,
+    /// The definition of the tool the client called, if the service lists it. It is not updated
+    /// when a middleware renames the call.
+    pub tool: Option<Tool>,
+    /// When the call entered the pipeline.
+    pub started_at: Instant,
+}
+
+/// Inspects, rewrites or refuses tool calls, and post-processes their results.
+///
+/// Returning [`Error::Rpc`](crate::error::Error::Rpc) from a hook sends that JSON-RPC error to the
+/// client. Any other error becomes a tool result with `is_error` set.
+#[async_trait]
+pub trait ToolMiddleware: Send + Sync {
+    /// Runs before the tool. It may rewrite the call, refuse it with an error, or answer it
+    /// directly by returning the content, in which case the tool is not called.
+    async fn before_call(&self, _call: &mut ToolCall) -> Result<Option<Vec<Content>>> {
+        Ok(None)
+    }
+
+    /// Runs after the tool, or after a later middleware answered or refused the call. It sees the
+    /// call as the tool received it.
+    async fn after_call(
+        &self,
+        _call: &ToolCall,
+        result: Result<Vec<Content>>,
+    ) -> Result<Vec<Content>> {
+        result
+    }
+
+    /// Runs instead of `after_call` when the call is dropped before it finished: cancelled by the
+    /// client, cut off at shutdown or by a timeout. It runs while the call is being dropped, so it
+    /// cannot await.
+    fn call_aborted(&self, _call: &ToolCall) {}
+}
+
+/// The call while it passes through the middlewares, telling those it entered when it is dropped
+/// before they saw its result.
+struct InFlight<'a> {
+    call: ToolCall,
+    middlewares: &'a [Arc<dyn ToolMiddleware>],
+    /// Middlewares whose `before_call` let the call through and whose `after_call` has not run.
+    entered: usize,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        for middleware in self.middlewares[.. self.entered].iter().rev() {
+            middleware.call_aborted(&self.call);
+        }
+    }
+}
+
+/// A [`Service`] whose tool calls pass through a chain of [`ToolMiddleware`].
+///
+/// `before_call` hooks run in the order the middlewares were added and `after_call` hooks in
+/// reverse, so the first middleware sees the final result. When a middleware answers or refuses
+/// a call, only the middlewares before it get their `after_call`, and when the call is dropped
+/// before it finished, they get `call_aborted` instead.
+///
+/// [`ToolLimits`] apply to the call itself, as the middlewares have left it.
+pub struct ToolPipeline {
+    inner: Arc<dyn Service>,
+    middlewares: Vec<Arc<dyn ToolMiddleware>>,
+    limits: Option<ToolLimits>,
+}
+
+impl ToolPipeline {
+    pub fn new(inner: Arc<dyn Service>) -> Self {
+        Self {
+            inner,
+            middlewares: Vec::new(),
+            limits: None,
+        }
+    }
+
+    pub fn with_middleware(mut self, middleware: Arc<dyn ToolMiddleware>) -> Self {
+        self.middlewares.push(middleware);
+        self
+    }
+
+    pub fn with_limits(mut self, limits: ToolLimits) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+}
+
+#[async_trait]
+impl Service for ToolPipeline {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn instructions(&self) -> String {
+        self.inner.instructions()
+    }
+
+    fn capabilities(&self) -> ServerCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn list_tools(&self) -> Vec<Tool> {
+        self.inner.list_tools()
+    }
+
+    async fn list_tools_page(&self, request: PageRequest) -> Result<Page<Tool>> {
+        self.inner.list_tools_page(request).await
+    }
+
+    async fn call_tool(
+        &self,
+        tool_name: &str,
+        arguments: Value,
+        ctx: RequestContext,
+    ) -> Result<Vec<Content>> {
+        let tool = if self.middlewares.is_empty() {
+            None
+        } else {
+            self.inner
+                .list_tools()
+                .into_iter()
+                .find(|tool| tool.name == tool_name)
+        };
+        let mut in_flight = InFlight {
+            call: ToolCall {
+                name: tool_name.to_string(),
+                arguments,
+                ctx,
+                tool,
+                started_at: Instant::now(),
+            },
+            middlewares: &self.middlewares,
+            entered: 0,
+        };
+
+        let mut answer = None;
+        for middleware in &self.middlewares {
+            match middleware.before_call(&mut in_flight.call).await {
+                Ok(None) => in_flight.entered += 1,
+                Ok(Some(content)) => {
+                    answer = Some(Ok(content));
+                    break;
+                }
+                Err(err) => {
+                    answer = Some(Err(err));
+                    break;
+                }
+            }
+        }
+
+        let mut result = match answer {
+            Some(result) => result,
+            None => {
+                let call = &in_flight.call;
+                let result =
+                    self.inner
+                        .call_tool(&call.name, call.arguments.clone(), call.ctx.clone());
+                match &self.limits {
+                    Some(limits) => limits.run(&call.name, result).await,
+                    None => result.await,
+                }
+            }
+        };
+        while in_flight.entered > 0 {
+            let middleware = &self.middlewares[in_flight.entered - 1];
+            result = middleware.after_call(&in_flight.call, result).await;
+            in_flight.entered -= 1;
+        }
+        result
+    }
+
+    fn list_resources(&self) -> Vec<Resource> {
+        self.inner.list_resources()
+    }
+
+    async fn list_resources_page(&self, request: PageRequest) -> Result<Page<Resource>> {
+        self.inner.list_resources_page(request).await
+    }
+
+    fn list_resource_templates(&self) -> Vec<ResourceTemplate> {
+        self.inner.list_resource_templates()
+    }
+
+    async fn list_resource_templates_page(
+        &self,
+        request: PageRequest,
+    ) -> Result<Page<ResourceTemplate>> {
+        self.inner.list_resource_templates_page(request).await
+    }
+
+    async fn read_resource(&self, uri: &str, ctx: RequestContext) -> Result<String> {
+        self.inner.read_resource(uri, ctx).await
+    }
+
+    fn list_prompts(&self) -> Vec<Prompt> {
+        self.inner.list_prompts()
+    }
+
+    async fn list_prompts_page(&self, request: PageRequest) -> Result<Page<Prompt>> {
+        self.inner.list_prompts_page(request).await
+    }
+
+    async fn get_prompt(&self, prompt_name: &str, ctx: RequestContext) -> Result<String> {
+        self.inner.get_prompt(prompt_name, ctx).await
+    }
+
+    fn logging_handler(&self) -> Option<&dyn LoggingHandler> {
+        self.inner.logging_handler()
+    }
+
+    fn completion_handler(&self) -> Option<&dyn CompletionHandler> {
+        self.inner.completion_handler()
+    }
+
+    fn subscription_handler(&self) -> Option<&dyn SubscriptionHandler> {
+        self.inner.subscription_handler()
+    }
+
+    fn subscribe_notifications(&self) -> Option<broadcast::Receiver<JsonRpcNotification>> {
+        self.inner.subscribe_notifications()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        error::Error,
+        service::registry::{Registry, RegistryService},
+    };
+
+    struct Rename;
+
+    #[async_trait]
+    impl ToolMiddleware for Rename {
+        async fn before_call(&self, call: &mut ToolCall) -> Result<Option<Vec<Content>>> {
+            if call.name == "old_echo" {
+                call.name = "echo".into();
+            }
+            Ok(None)
+        }
+
+        async fn after_call(
+            &self,
+            _call: &ToolCall,
+            result: Result<Vec<Content>>,
+        ) -> Result<Vec<Content>> {
+            let mut content = result?;
+            content.push(Content::text("renamed"));
+            Ok(content)
+        }
+    }
+
+    struct Deny;
+
+    #[async_trait]
+    impl ToolMiddleware for Deny {
+        async fn before_call(&self, call: &mut ToolCall) -> Result<Option<Vec<Content>>> {
+            if call.arguments["secret"].is_string() {
+                return Err(Error::rpc_with_data(
+                    -32001,
+                    "Forbidden",
+                    json!({ "field": "secret" }),
+                ));
+            }
+            Ok(None)
+        }
+
+        async fn after_call(
+            &self,
+            _call: &ToolCall,
+            result: Result<Vec<Content>>,
+        ) -> Result<Vec<Content>> {
+            Ok(result?
+                .into_iter()
+                .map(|c| match c.as_text() {
+                    Some(text) => Content::text(text.replace("hunter2", "***")),
+                    None => c,
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middlewares_rewrite_refuse_and_post_process() {
+        let registry = Arc::new(Registry::new());
+        registry.add_tool(
+            Tool::new("echo", "", json!({ "type": "object" })),
+            |args, _| async move { Ok(vec![Content::text(args["text"].to_string())]) },
+        );
+        let pipeline = ToolPipeline::new(Arc::new(RegistryService::new("echo", "", registry)))
+            .with_middleware(Arc::new(Rename))
+            .with_middleware(Arc::new(Deny));
+
+        let content = pipeline
+            .call_tool(
+                "old_echo",
+                json!({ "text": "hunter2" }),
+                RequestContext::default(),
+            )
+            .await
+            .unwrap();
+        let texts: Vec<_> = content.iter().filter_map(Content::as_text).collect();
+        assert_eq!(texts, ["\"***\"", "renamed"]);
+
+        let err = pipeline
+            .call_tool("echo", json!({ "secret": "x" }), RequestContext::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Rpc { code: -32001, .. }));
+    }
+}
@@ -1,5 +1,14 @@
+pub mod audit;
+pub mod cache;
 pub mod capabilities;
+pub mod composite;
+pub mod context;
 pub mod ext;
 pub mod impls;
-pub mod router_variant;
+pub mod layer;
+pub mod limits;
+pub mod middleware;
+pub mod pagination;
+pub mod rate_limit;
+pub mod registry;
 pub mod traits;
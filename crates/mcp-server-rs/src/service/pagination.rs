@@ -0,0 +1,228 @@
+use std::sync::Arc;
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+
+use crate::error::{Error, Result};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Number of items returned per page when nothing else is configured.
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// The list a cursor was issued for. A cursor is only accepted by the list that produced it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListKind {
+    Tools,
+    Resources,
+    ResourceTemplates,
+    Prompts,
+}
+
+/// A request for a single page of a list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRequest {
+    /// Position to resume from, exactly as returned in a previous [`Page::next_cursor`]. `None`
+    /// requests the first page.
+    pub cursor: Option<String>,
+    /// Maximum number of items to return.
+    pub limit: usize,
+}
+
+impl PageRequest {
+    pub fn first(limit: usize) -> Self {
+        Self {
+            cursor: None,
+            limit,
+        }
+    }
+}
+
+/// One page of a list, as produced by a `Service`.
+///
+/// `next_cursor` is a service-defined position (an offset, an S3 continuation token, ...). The
+/// server signs it before handing it to the client, so services never see forged positions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, next_cursor: Option<String>) -> Self {
+        Self { items, next_cursor }
+    }
+
+    /// A page with no further pages after it.
+    pub fn last(items: Vec<T>) -> Self {
+        Self::new(items, None)
+    }
+}
+
+/// Slices a fully materialized list, using the item offset as the cursor position.
+pub fn paginate<T>(items: Vec<T>, request: &PageRequest) -> Result<Page<T>> {
+    let offset = match &request.cursor {
+        Some(cursor) => cursor
+            .parse::<usize>()
+            .map_err(|_| Error::InvalidParameters("Invalid cursor".into()))?,
+        None => 0,
+    };
+    let total = items.len();
+    let end = offset.saturating_add(request.limit).min(total);
+    let next_cursor = (end < total).then(|| end.to_string());
+    let items = items.into_iter().skip(offset).take(request.limit).collect();
+
+    Ok(Page::new(items, next_cursor))
+}
+
+#[derive(Serialize, Deserialize)]
+struct CursorPayload {
+    #[serde(rename = "k")]
+    kind: ListKind,
+    #[serde(rename = "p")]
+    position: String,
+}
+
+/// Page size and signing key used to issue and verify opaque list cursors.
+#[derive(Clone)]
+pub struct Pagination {
+    key: Arc<[u8]>,
+    page_size: usize,
+}
+
+impl Default for Pagination {
+    /// Uses a random per-process key, so cursors do not survive a server restart.
+    fn default() -> Self {
+        Self::new(rand::random::<[u8; 32]>(), DEFAULT_PAGE_SIZE)
+    }
+}
+
+impl Pagination {
+    pub fn new(key: impl AsRef<[u8]>, page_size: usize) -> Self {
+        Self {
+            key: Arc::from(key.as_ref()),
+            page_size: page_size.max(1),
+        }
+    }
+
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Sets the key used to sign cursors. Servers behind a load balancer must share it.
+    pub fn with_key(mut self, key: impl AsRef<[u8]>) -> Self {
+        self.key = Arc::from(key.as_ref());
+        self
+    }
+
+    /// Builds the page request for a `*/list` call from its `params.cursor`.
+    pub fn page_request(&self, kind: ListKind, params: Option<&Value>) -> Result<PageRequest> {
+        let cursor = match params.and_then(|p| p.get("cursor")) {
+            None | Some(Value::Null) => None,
+            Some(Value::String(cursor)) => Some(self.decode(kind, cursor)?),
+            Some(_) => {
+                return Err(Error::InvalidParameters("Cursor must be a string".into()));
+            }
+        };
+
+        Ok(PageRequest {
+            cursor,
+            limit: self.page_size,
+        })
+    }
+
+    /// Turns a service-defined position into an opaque, signed cursor.
+    pub fn encode(&self, kind: ListKind, position: &str) -> String {
+        let payload = serde_json::to_vec(&CursorPayload {
+            kind,
+            position: position.to_string(),
+        })
+        .expect("cursor payload is always serializable");
+        let signature = self.mac(&payload).finalize().into_bytes();
+
+        format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(payload),
+            URL_SAFE_NO_PAD.encode(signature)
+        )
+    }
+
+    /// Verifies a cursor issued by [`Pagination::encode`] and returns its position.
+    pub fn decode(&self, kind: ListKind, cursor: &str) -> Result<String> {
+        let invalid = || Error::InvalidParameters("Invalid cursor".into());
+
+        let (payload, signature) = cursor.split_once('.').ok_or_else(invalid)?;
+        let payload = URL_SAFE_NO_PAD.decode(payload).map_err(|_| invalid())?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid())?;
+        self.mac(&payload)
+            .verify_slice(&signature)
+            .map_err(|_| invalid())?;
+
+        let payload: CursorPayload = serde_json::from_slice(&payload).map_err(|_| invalid())?;
+        if payload.kind != kind {
+            return Err(invalid());
+        }
+        Ok(payload.position)
+    }
+
+    fn mac(&self, payload: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any size");
+        mac.update(payload);
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_paginate_walks_all_items() {
+        let items: Vec<u32> = (0 .. 5).collect();
+        let first = paginate(items.clone(), &PageRequest::first(2)).unwrap();
+        assert_eq!(first.items, vec![0, 1]);
+
+        let mut request = PageRequest::first(2);
+        request.cursor = first.next_cursor;
+        let second = paginate(items.clone(), &request).unwrap();
+        assert_eq!(second.items, vec![2, 3]);
+
+        request.cursor = second.next_cursor;
+        let last = paginate(items, &request).unwrap();
+        assert_eq!(last.items, vec![4]);
+        assert_eq!(last.next_cursor, None);
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let pagination = Pagination::new(b"secret", 10);
+        let cursor = pagination.encode(ListKind::Tools, "20");
+        let request = pagination
+            .page_request(ListKind::Tools, Some(&json!({ "cursor": cursor })))
+            .unwrap();
+        assert_eq!(request.cursor.as_deref(), Some("20"));
+        assert_eq!(request.limit, 10);
+    }
+
+    #[test]
+    fn test_cursor_rejects_tampering() {
+        let pagination = Pagination::new(b"secret", 10);
+        let cursor = pagination.encode(ListKind::Tools, "20");
+
+        let forged = Pagination::new(b"other", 10).encode(ListKind::Tools, "20");
+        assert!(pagination.decode(ListKind::Tools, &forged).is_err());
+        assert!(pagination.decode(ListKind::Prompts, &cursor).is_err());
+        assert!(pagination.decode(ListKind::Tools, "20").is_err());
+    }
+}
@@ -0,0 +1,376 @@
+//! Token-bucket rate limiting of requests, per caller, as a tower [`Layer`].
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, RwLock},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures::future::BoxFuture;
+use serde_json::Value;
+use tower::Layer;
+
+use crate::{
+    core::protocol::message::JsonRpcResponse,
+    error::{Error, Result},
+    service::{
+        context::RequestContext,
+        layer::{BoxError, McpRequest},
+    },
+};
+
+/// Buckets tracked before full ones are dropped.
+const PRUNE_THRESHOLD: usize = 4096;
+
+/// A budget of `requests` per `period`, with bursts of up to `burst` requests.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    requests: u32,
+    period: Duration,
+    burst: u32,
+}
+
+impl RateLimit {
+    /// Allows `requests` per `period`, all of which may come at once.
+    pub fn new(requests: u32, period: Duration) -> Self {
+        let requests = requests.max(1);
+        Self {
+            requests,
+            period,
+            burst: requests,
+        }
+    }
+
+    pub fn per_second(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(1))
+    }
+
+    pub fn per_minute(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(60))
+    }
+
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    pub fn requests(&self) -> u32 {
+        self.requests
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+
+    /// Tokens added per second, never zero.
+    fn rate(&self) -> f64 {
+        self.requests.max(1) as f64 / self.period.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// How long it takes to add `tokens`.
+    fn time_for(&self, tokens: f64) -> Duration {
+        Duration::try_from_secs_f64(tokens / self.rate()).unwrap_or(Duration::MAX)
+    }
+}
+
+/// Who a budget belongs to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RateLimitKey {
+    /// The authenticated subject, so that every session of one caller shares a budget. Falls
+    /// back to the session for unauthenticated requests.
+    #[default]
+    Identity,
+    /// The session, even when the caller is authenticated.
+    Session,
+}
+
+impl RateLimitKey {
+    /// `None` for requests outside of any session, such as direct calls to a service.
+    fn of(&self, ctx: &RequestContext) -> Option<String> {
+        let subject = match self {
+            RateLimitKey::Identity => ctx.auth.as_ref().map(|auth| &auth.subject),
+            RateLimitKey::Session => None,
+        };
+        match (subject, &ctx.session_id) {
+            (Some(subject), _) => Some(format!("sub:{subject}")),
+            (None, Some(session_id)) => Some(format!("session:{session_id}")),
+            (None, None) => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Config {
+    key: RateLimitKey,
+    default: Option<RateLimit>,
+    methods: HashMap<String, RateLimit>,
+    tools: HashMap<String, RateLimit>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// When the bucket is back to its burst, after which it can be forgotten.
+    full_at: Instant,
+}
+
+impl Bucket {
+    /// Refills the bucket and returns how long until a token is available.
+    fn refill(&mut self, limit: &RateLimit, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.rate()).min(limit.burst.max(1) as f64);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            limit.time_for(1.0 - self.tokens)
+        }
+    }
+}
+
+/// Per-caller request budgets: an overall one, one per method and one per tool.
+///
+/// A request must fit in every budget that applies to it, and only then takes from each. Limited
+/// requests fail with [`Error::RateLimited`], which reaches the client as a JSON-RPC error whose
+/// data says how long to wait. Limits can be changed at any time, also while serving; clones
+/// share their limits and buckets, so one limiter can be given to the servers of every session.
+/// Add it with [`Server::with_layer`](crate::server::Server::with_layer).
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    config: Arc<RwLock<Config>>,
+    buckets: Arc<Mutex<HashMap<(String, String), Bucket>>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_key(self, key: RateLimitKey) -> Self {
+        self.config.write().unwrap().key = key;
+        self
+    }
+
+    /// Sets the budget shared by all requests of a caller.
+    pub fn with_default(self, limit: RateLimit) -> Self {
+        self.set_default(Some(limit));
+        self
+    }
+
+    pub fn with_method(self, method: impl Into<String>, limit: RateLimit) -> Self {
+        self.set_method(method, Some(limit));
+        self
+    }
+
+    /// Sets the budget for `tools/call` requests of one tool.
+    pub fn with_tool(self, tool: impl Into<String>, limit: RateLimit) -> Self {
+        self.set_tool(tool, Some(limit));
+        self
+    }
+
+    /// Replaces or, with `None`, removes the overall budget.
+    pub fn set_default(&self, limit: Option<RateLimit>) {
+        self.config.write().unwrap().default = limit;
+    }
+
+    /// Replaces or, with `None`, removes the budget of `method`.
+    pub fn set_method(&self, method: impl Into<String>, limit: Option<RateLimit>) {
+        let mut config = self.config.write().unwrap();
+        match limit {
+            Some(limit) => config.methods.insert(method.into(), limit),
+            None => config.methods.remove(&method.into()),
+        };
+    }
+
+    /// Replaces or, with `None`, removes the budget of `tool`.
+    pub fn set_tool(&self, tool: impl Into<String>, limit: Option<RateLimit>) {
+        let mut config = self.config.write().unwrap();
+        match limit {
+            Some(limit) => config.tools.insert(tool.into(), limit),
+            None => config.tools.remove(&tool.into()),
+        };
+    }
+
+    /// Takes one request from every budget that applies, or fails with the longest wait among
+    /// those that are exhausted.
+    pub fn check(&self, method: &str, tool: Option<&str>, ctx: &RequestContext) -> Result<()> {
+        let config = self.config.read().unwrap();
+        let scopes: Vec<(String, RateLimit)> = [
+            config.default.map(|limit| ("*".to_string(), limit)),
+            config
+                .methods
+                .get(method)
+                .map(|limit| (format!("method:{method}"), *limit)),
+            tool.and_then(|tool| {
+                config
+                    .tools
+                    .get(tool)
+                    .map(|limit| (format!("tool:{tool}"), *limit))
+            }),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if scopes.is_empty() {
+            return Ok(());
+        }
+        let Some(key) = config.key.of(ctx) else {
+            return Ok(());
+        };
+        drop(config);
+
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() > PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| bucket.full_at > now);
+        }
+        let mut exhausted: Option<(&str, Duration)> = None;
+        for (scope, limit) in &scopes {
+            let bucket = buckets
+                .entry((key.clone(), scope.clone()))
+                .or_insert_with(|| Bucket {
+                    tokens: limit.burst.max(1) as f64,
+                    updated: now,
+                    full_at: now,
+                });
+            let wait = bucket.refill(limit, now);
+            if wait > exhausted.map_or(Duration::ZERO, |(_, wait)| wait) {
+                exhausted = Some((scope, wait));
+            }
+        }
+        if let Some((scope, retry_after)) = exhausted {
+            tracing::debug!(%key, scope, ?retry_after, "Request rate limited");
+            return Err(Error::RateLimited {
+                scope: scope.to_string(),
+                retry_after,
+            });
+        }
+        for (scope, limit) in &scopes {
+            if let Some(bucket) = buckets.get_mut(&(key.clone(), scope.clone())) {
+                bucket.tokens -= 1.0;
+                let refill = limit.time_for(limit.burst.max(1) as f64 - bucket.tokens);
+                bucket.full_at = now.checked_add(refill).unwrap_or(now);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S> Layer<S> for RateLimiter {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            limiter: self.clone(),
+        }
+    }
+}
+
+/// The service produced by a [`RateLimiter`] layer.
+#[derive(Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    limiter: RateLimiter,
+}
+
+impl<S> tower::Service<McpRequest> for RateLimitService<S>
+where
+    S: tower::Service<McpRequest, Response = JsonRpcResponse>,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, std::result::Result<JsonRpcResponse, BoxError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), BoxError>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: McpRequest) -> Self::Future {
+        let request = &req.request;
+        let tool = match request.method.as_str() {
+            "tools/call" => request
+                .params
+                .as_ref()
+                .and_then(|params| params.get("name"))
+                .and_then(Value::as_str),
+            _ => None,
+        };
+        if let Err(e) = self.limiter.check(&request.method, tool, &req.ctx) {
+            return Box::pin(async move { Err(e.into()) });
+        }
+        let response = self.inner.call(req);
+        Box::pin(async move { response.await.map_err(Into::into) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::protocol::{constants::RATE_LIMITED, error::ErrorData},
+        service::context::AuthIdentity,
+    };
+
+    #[test]
+    fn test_budgets_per_caller_and_tool() {
+        let limiter = RateLimiter::new()
+            .with_method("tools/call", RateLimit::per_minute(3))
+            .with_tool("search", RateLimit::per_minute(1));
+        let alice = RequestContext {
+            session_id: Some("s1".into()),
+            auth: Some(AuthIdentity::new("alice")),
+            ..Default::default()
+        };
+        let other_session = RequestContext {
+            session_id: Some("s2".into()),
+            ..alice.clone()
+        };
+        let bob = RequestContext {
+            auth: Some(AuthIdentity::new("bob")),
+            ..alice.clone()
+        };
+
+        assert!(limiter.check("tools/call", Some("search"), &alice).is_ok());
+        let err = limiter
+            .check("tools/call", Some("search"), &other_session)
+            .unwrap_err();
+        let data = ErrorData::from(&err);
+        assert_eq!(data.code, RATE_LIMITED);
+        assert_eq!(data.data.as_ref().unwrap()["scope"], "tool:search");
+        assert!(data.data.unwrap()["retryAfterMs"].as_u64().unwrap() > 0);
+
+        assert!(limiter.check("tools/call", Some("echo"), &alice).is_ok());
+        assert!(limiter.check("tools/call", Some("echo"), &alice).is_ok());
+        assert!(limiter.check("tools/call", Some("echo"), &alice).is_err());
+        assert!(limiter.check("tools/list", None, &alice).is_ok());
+        assert!(limiter.check("tools/call", Some("search"), &bob).is_ok());
+
+        limiter.set_method("tools/call", None);
+        assert!(limiter.check("tools/call", Some("echo"), &alice).is_ok());
+    }
+
+    #[test]
+    fn test_degenerate_limits() {
+        let ctx = RequestContext {
+            session_id: Some("s1".into()),
+            ..Default::default()
+        };
+        let limit = RateLimit::new(0, Duration::ZERO).with_burst(0);
+        assert_eq!((limit.requests(), limit.burst()), (1, 1));
+
+        let limiter = RateLimiter::new()
+            .with_default(limit)
+            .with_tool("slow", RateLimit::new(1, Duration::MAX));
+        assert!(limiter.check("ping", None, &ctx).is_ok());
+        assert!(limiter.check("tools/call", Some("slow"), &ctx).is_ok());
+        assert!(limiter.check("tools/call", Some("slow"), &ctx).is_err());
+    }
+}
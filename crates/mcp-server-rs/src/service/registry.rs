@@ -0,0 +1,283 @@
+use std::{future::Future, sync::Arc};
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+use crate::{
+    core::{
+        Resource, Tool,
+        content::Content,
+        prompt::Prompt,
+        protocol::{capabilities::ServerCapabilities, message::JsonRpcNotification},
+    },
+    error::{Error, Result},
+    service::{capabilities::CapabilitiesBuilder, context::RequestContext, traits::Service},
+};
+
+const TOOLS_LIST_CHANGED: &str = "notifications/tools/list_changed";
+const PROMPTS_LIST_CHANGED: &str = "notifications/prompts/list_changed";
+const RESOURCES_LIST_CHANGED: &str = "notifications/resources/list_changed";
+
+/// How many undelivered notifications a slow session may fall behind before it starts skipping.
+const NOTIFICATION_BUFFER: usize = 64;
+
+pub type ToolHandler =
+    Arc<dyn Fn(Value, RequestContext) -> BoxFuture<'static, Result<Vec<Content>>> + Send + Sync>;
+pub type PromptHandler =
+    Arc<dyn Fn(RequestContext) -> BoxFuture<'static, Result<String>> + Send + Sync>;
+pub type ResourceHandler =
+    Arc<dyn Fn(RequestContext) -> BoxFuture<'static, Result<String>> + Send + Sync>;
+
+/// Tools, prompts and resources that can change while sessions are connected.
+///
+/// Share one registry (behind an `Arc`) between every session's service. Each add or remove
+/// broadcasts the matching `notifications/*/list_changed` to all subscribed sessions.
+pub struct Registry {
+    tools: std::sync::RwLock<Vec<(Tool, ToolHandler)>>,
+    prompts: std::sync::RwLock<Vec<(Prompt, PromptHandler)>>,
+    resources: std::sync::RwLock<Vec<(Resource, ResourceHandler)>>,
+    notifier: broadcast::Sender<JsonRpcNotification>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        let (notifier, _) = broadcast::channel(NOTIFICATION_BUFFER);
+        Self {
+            tools: Default::default(),
+            prompts: Default::default(),
+            resources: Default::default(),
+            notifier,
+        }
+    }
+
+    /// Adds a tool, replacing any existing tool with the same name.
+    pub fn add_tool<F, Fut>(&self, tool: Tool, handler: F)
+    where
+        F: Fn(Value, RequestContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Vec<Content>>> + Send + 'static,
+    {
+        let handler: ToolHandler = Arc::new(move |args, ctx| Box::pin(handler(args, ctx)));
+        upsert(&self.tools, tool, handler, |t| t.name.clone());
+        self.notify(TOOLS_LIST_CHANGED);
+    }
+
+    /// Removes a tool by name. Returns whether a tool was removed.
+    pub fn remove_tool(&self, name: &str) -> bool {
+        let removed = remove(&self.tools, |t| t.name == name);
+        if removed {
+            self.notify(TOOLS_LIST_CHANGED);
+        }
+        removed
+    }
+
+    /// Adds a prompt, replacing any existing prompt with the same name.
+    pub fn add_prompt<F, Fut>(&self, prompt: Prompt, handler: F)
+    where
+        F: Fn(RequestContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String>> + Send + 'static,
+    {
+        let handler: PromptHandler = Arc::new(move |ctx| Box::pin(handler(ctx)));
+        upsert(&self.prompts, prompt, handler, |p| p.name.clone());
+        self.notify(PROMPTS_LIST_CHANGED);
+    }
+
+    /// Removes a prompt by name. Returns whether a prompt was removed.
+    pub fn remove_prompt(&self, name: &str) -> bool {
+        let removed = remove(&self.prompts, |p| p.name == name);
+        if removed {
+            self.notify(PROMPTS_LIST_CHANGED);
+        }
+        removed
+    }
+
+    /// Adds a resource, replacing any existing resource with the same URI.
+    pub fn add_resource<F, Fut>(&self, resource: Resource, handler: F)
+    where
+        F: Fn(RequestContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String>> + Send + 'static,
+    {
+        let handler: ResourceHandler = Arc::new(move |ctx| Box::pin(handler(ctx)));
+        upsert(&self.resources, resource, handler, |r| r.uri.clone());
+        self.notify(RESOURCES_LIST_CHANGED);
+    }
+
+    /// Removes a resource by URI. Returns whether a resource was removed.
+    pub fn remove_resource(&self, uri: &str) -> bool {
+        let removed = remove(&self.resources, |r| r.uri == uri);
+        if removed {
+            self.notify(RESOURCES_LIST_CHANGED);
+        }
+        removed
+    }
+
+    pub fn tools(&self) -> Vec<Tool> {
+        snapshot(&self.tools)
+    }
+
+    pub fn prompts(&self) -> Vec<Prompt> {
+        snapshot(&self.prompts)
+    }
+
+    pub fn resources(&self) -> Vec<Resource> {
+        snapshot(&self.resources)
+    }
+
+    pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Value,
+        ctx: RequestContext,
+    ) -> Result<Vec<Content>> {
+        let handler = find(&self.tools, |t| t.name == name)
+            .ok_or_else(|| Error::System(format!("Tool {} not found", name)))?;
+        handler(arguments, ctx).await
+    }
+
+    pub async fn get_prompt(&self, name: &str, ctx: RequestContext) -> Result<String> {
+        let handler = find(&self.prompts, |p| p.name == name)
+            .ok_or_else(|| Error::InvalidParameters(format!("Prompt '{}' not found", name)))?;
+        handler(ctx).await
+    }
+
+    pub async fn read_resource(&self, uri: &str, ctx: RequestContext) -> Result<String> {
+        let handler = find(&self.resources, |r| r.uri == uri)
+            .ok_or_else(|| Error::ResourceNotFound(uri.to_string()))?;
+        handler(ctx).await
+    }
+
+    /// Subscribes to the `list_changed` notifications emitted by this registry.
+    pub fn subscribe(&self) -> broadcast::Receiver<JsonRpcNotification> {
+        self.notifier.subscribe()
+    }
+
+    fn notify(&self, method: &str) {
+        // No receivers just means no session is connected right now.
+        let _ = self.notifier.send(JsonRpcNotification::new(method, None));
+    }
+}
+
+fn upsert<T, H>(
+    entries: &std::sync::RwLock<Vec<(T, H)>>,
+    item: T,
+    handler: H,
+    key: impl Fn(&T) -> String,
+) {
+    let mut entries = entries.write().unwrap();
+    let item_key = key(&item);
+    match entries
+        .iter_mut()
+        .find(|(existing, _)| key(existing) == item_key)
+    {
+        Some(entry) => *entry = (item, handler),
+        None => entries.push((item, handler)),
+    }
+}
+
+fn remove<T, H>(entries: &std::sync::RwLock<Vec<(T, H)>>, matches: impl Fn(&T) -> bool) -> bool {
+    let mut entries = entries.write().unwrap();
+    let before = entries.len();
+    entries.retain(|(item, _)| !matches(item));
+    entries.len() != before
+}
+
+fn find<T, H: Clone>(
+    entries: &std::sync::RwLock<Vec<(T, H)>>,
+    matches: impl Fn(&T) -> bool,
+) -> Option<H> {
+    let entries = entries.read().unwrap();
+    entries
+        .iter()
+        .find(|(item, _)| matches(item))
+        .map(|(_, handler)| handler.clone())
+}
+
+fn snapshot<T: Clone, H>(entries: &std::sync::RwLock<Vec<(T, H)>>) -> Vec<T> {
+    let entries = entries.read().unwrap();
+    entries.iter().map(|(item, _)| item.clone()).collect()
+}
+
+/// A `Service` backed by a shared [`Registry`].
+#[derive(Clone)]
+pub struct RegistryService {
+    name: String,
+    instructions: String,
+    registry: Arc<Registry>,
+}
+
+impl RegistryService {
+    pub fn new(
+        name: impl Into<String>,
+        instructions: impl Into<String>,
+        registry: Arc<Registry>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            instructions: instructions.into(),
+            registry,
+        }
+    }
+
+    pub fn registry(&self) -> &Arc<Registry> {
+        &self.registry
+    }
+}
+
+#[async_trait]
+impl Service for RegistryService {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn instructions(&self) -> String {
+        self.instructions.clone()
+    }
+
+    fn capabilities(&self) -> ServerCapabilities {
+        CapabilitiesBuilder::new()
+            .with_tools(true)
+            .with_resources(false, true)
+            .with_prompts(true)
+            .build()
+    }
+
+    fn list_tools(&self) -> Vec<Tool> {
+        self.registry.tools()
+    }
+
+    async fn call_tool(
+        &self,
+        tool_name: &str,
+        arguments: Value,
+        ctx: RequestContext,
+    ) -> Result<Vec<Content>> {
+        self.registry.call_tool(tool_name, arguments, ctx).await
+    }
+
+    fn list_resources(&self) -> Vec<Resource> {
+        self.registry.resources()
+    }
+
+    async fn read_resource(&self, uri: &str, ctx: RequestContext) -> Result<String> {
+        self.registry.read_resource(uri, ctx).await
+    }
+
+    fn list_prompts(&self) -> Vec<Prompt> {
+        self.registry.prompts()
+    }
+
+    async fn get_prompt(&self, prompt_name: &str, ctx: RequestContext) -> Result<String> {
+        self.registry.get_prompt(prompt_name, ctx).await
+    }
+
+    fn subscribe_notifications(&self) -> Option<broadcast::Receiver<JsonRpcNotification>> {
+        Some(self.registry.subscribe())
+    }
+}
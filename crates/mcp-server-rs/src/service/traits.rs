@@ -1,12 +1,25 @@
 use async_trait::async_trait;
 use serde_json::Value;
+use tokio::sync::broadcast;
 
 use crate::{
     core::{
-        Resource, Tool, content::Content, prompt::Prompt,
-        protocol::capabilities::ServerCapabilities,
+        Resource, ResourceTemplate, Tool,
+        content::Content,
+        prompt::Prompt,
+        protocol::{
+            capabilities::ServerCapabilities,
+            completion::{Completion, CompletionArgument, CompletionReference},
+            logging::LoggingLevel,
+            message::JsonRpcNotification,
+        },
     },
     error::{Error, Result},
+    service::{
+        capabilities::infer_capabilities,
+        context::RequestContext,
+        pagination::{Page, PageRequest, paginate},
+    },
 };
 
 #[async_trait]
@@ -15,17 +28,50 @@ pub trait Service: Send + Sync {
 
     fn instructions(&self) -> String;
 
-    fn capabilities(&self) -> ServerCapabilities;
+    /// What the server advertises during `initialize`.
+    ///
+    /// By default this is inferred from what the service provides when it is called; see
+    /// [`infer_capabilities`]. Override it when the lists change at runtime or are only available
+    /// through the `*_page` methods.
+    fn capabilities(&self) -> ServerCapabilities {
+        infer_capabilities(self)
+    }
 
     fn list_tools(&self) -> Vec<Tool>;
 
-    async fn call_tool(&self, tool_name: &str, arguments: Value) -> Result<Vec<Content>>;
+    /// Returns one page of tools. Override this instead of `list_tools` when the full list is too
+    /// large or too expensive to build on every request.
+    async fn list_tools_page(&self, request: PageRequest) -> Result<Page<Tool>> {
+        paginate(self.list_tools(), &request)
+    }
+
+    async fn call_tool(
+        &self,
+        tool_name: &str,
+        arguments: Value,
+        ctx: RequestContext,
+    ) -> Result<Vec<Content>>;
 
     fn list_resources(&self) -> Vec<Resource> {
         vec![]
     }
 
-    async fn read_resource(&self, _uri: &str) -> Result<String> {
+    async fn list_resources_page(&self, request: PageRequest) -> Result<Page<Resource>> {
+        paginate(self.list_resources(), &request)
+    }
+
+    fn list_resource_templates(&self) -> Vec<ResourceTemplate> {
+        vec![]
+    }
+
+    async fn list_resource_templates_page(
+        &self,
+        request: PageRequest,
+    ) -> Result<Page<ResourceTemplate>> {
+        paginate(self.list_resource_templates(), &request)
+    }
+
+    async fn read_resource(&self, _uri: &str, _ctx: RequestContext) -> Result<String> {
         Err(Error::System(
             "No resources implemented for this server.".into(),
         ))
@@ -35,9 +81,59 @@ pub trait Service: Send + Sync {
         vec![]
     }
 
-    async fn get_prompt(&self, _prompt_name: &str) -> Result<String> {
+    async fn list_prompts_page(&self, request: PageRequest) -> Result<Page<Prompt>> {
+        paginate(self.list_prompts(), &request)
+    }
+
+    async fn get_prompt(&self, _prompt_name: &str, _ctx: RequestContext) -> Result<String> {
         Err(Error::System(
             "No prompts implemented for this server.".into(),
         ))
     }
+
+    /// Handles `logging/setLevel`. Services that send log messages return `Some`.
+    fn logging_handler(&self) -> Option<&dyn LoggingHandler> {
+        None
+    }
+
+    /// Handles `completion/complete` for prompt and resource template arguments.
+    fn completion_handler(&self) -> Option<&dyn CompletionHandler> {
+        None
+    }
+
+    /// Handles `resources/subscribe` and `resources/unsubscribe`.
+    fn subscription_handler(&self) -> Option<&dyn SubscriptionHandler> {
+        None
+    }
+
+    /// Notifications the server should push to the client unprompted, such as
+    /// `notifications/tools/list_changed`. Each session subscribes once when it starts.
+    fn subscribe_notifications(&self) -> Option<broadcast::Receiver<JsonRpcNotification>> {
+        None
+    }
+}
+
+#[async_trait]
+pub trait LoggingHandler: Send + Sync {
+    /// Sets the minimum level of the `notifications/message` sent to the session in `ctx`.
+    async fn set_level(&self, level: LoggingLevel, ctx: RequestContext) -> Result<()>;
+}
+
+#[async_trait]
+pub trait CompletionHandler: Send + Sync {
+    async fn complete(
+        &self,
+        reference: CompletionReference,
+        argument: CompletionArgument,
+        ctx: RequestContext,
+    ) -> Result<Completion>;
+}
+
+/// Tracks which resources a session wants `notifications/resources/updated` for. Sending the
+/// notifications is up to the service, e.g. through [`Service::subscribe_notifications`].
+#[async_trait]
+pub trait SubscriptionHandler: Send + Sync {
+    async fn subscribe(&self, uri: &str, ctx: RequestContext) -> Result<()>;
+
+    async fn unsubscribe(&self, uri: &str, ctx: RequestContext) -> Result<()>;
 }
@@ -0,0 +1,71 @@
+//! Shutting down every session of a transport together.
+
+use std::{future::Future, time::Duration};
+
+use tokio::task::JoinHandle;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+/// Tracks the sessions of one or more transports so that they can be shut down together.
+///
+/// Sessions run with [`ShutdownCoordinator::track`] and stop on [`ShutdownCoordinator::cancelled`].
+/// Once [`ShutdownCoordinator::shutdown`] is called, transports refuse new sessions and every
+/// running [`Server`](crate::server::Server) stops reading, drains its in-flight requests and
+/// closes its transport. Clones share their state.
+#[derive(Clone, Default)]
+pub struct ShutdownCoordinator {
+    token: CancellationToken,
+    sessions: TaskTracker,
+}
+
+impl ShutdownCoordinator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancelled once shutdown begins. Hand it to the HTTP server as well, e.g. to
+    /// `axum::serve(..).with_graceful_shutdown(token.cancelled_owned())`.
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Resolves once shutdown begins.
+    pub fn cancelled(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.token.clone().cancelled_owned()
+    }
+
+    /// Sessions that have not finished yet.
+    pub fn session_count(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Spawns a session, which [`ShutdownCoordinator::shutdown`] then waits for.
+    pub fn track<F>(&self, session: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.sessions.spawn(session)
+    }
+
+    /// Begins shutdown and waits up to `timeout` for every session to finish. Returns whether
+    /// they all did.
+    pub async fn shutdown(&self, timeout: Duration) -> bool {
+        tracing::info!(sessions = self.sessions.len(), "Shutting down all sessions");
+        self.token.cancel();
+        self.sessions.close();
+        let finished = tokio::time::timeout(timeout, self.sessions.wait())
+            .await
+            .is_ok();
+        if !finished {
+            tracing::warn!(
+                sessions = self.sessions.len(),
+                "Sessions still running after the shutdown timeout"
+            );
+        }
+        finished
+    }
+}
@@ -0,0 +1,526 @@
+//! Bearer-token authentication for the HTTP transports, which act as an OAuth 2.1 resource
+//! server.
+//!
+//! A [`BearerAuth`] checks the `Authorization: Bearer` header of every request with a
+//! [`TokenValidator`]. The identity of the request that opened a session is handed to the
+//! session's handlers as [`RequestContext::auth`](crate::service::context::RequestContext), and
+//! later requests must carry a valid token for the same subject. Failures are answered with a
+//! `WWW-Authenticate` challenge pointing at the protected resource metadata (RFC 9728), which
+//! [`BearerAuth::metadata_router`] serves.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::{
+    Json, Router,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{error::Result, service::context::AuthIdentity};
+
+/// Where the protected resource metadata of a resource at the root of its host is served.
+pub const PROTECTED_RESOURCE_METADATA_PATH: &str = "/.well-known/oauth-protected-resource";
+
+/// Checks access tokens.
+#[async_trait]
+pub trait TokenValidator: Send + Sync + 'static {
+    /// Returns who `token` was issued to, or fails with
+    /// [`Error::Unauthorized`](crate::error::Error::Unauthorized).
+    async fn validate(&self, token: &str) -> Result<AuthIdentity>;
+}
+
+/// OAuth 2.0 Protected Resource Metadata (RFC 9728), telling clients where to get tokens.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProtectedResourceMetadata {
+    /// The URL of the MCP endpoint, e.g. `https://mcp.example.com/mcp`.
+    pub resource: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authorization_servers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes_supported: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bearer_methods_supported: Vec<String>,
+}
+
+impl ProtectedResourceMetadata {
+    pub fn new(resource: impl Into<String>) -> Self {
+        Self {
+            resource: resource.into(),
+            authorization_servers: Vec::new(),
+            scopes_supported: Vec::new(),
+            bearer_methods_supported: vec!["header".to_string()],
+        }
+    }
+
+    pub fn with_authorization_server(mut self, issuer: impl Into<String>) -> Self {
+        self.authorization_servers.push(issuer.into());
+        self
+    }
+
+    pub fn with_scopes_supported<I, S>(mut self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.scopes_supported = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// The path the metadata is served at: the well-known prefix followed by the resource's own
+    /// path, as RFC 9728 specifies.
+    pub fn path(&self) -> String {
+        let (_, path) = split_origin(&self.resource);
+        format!(
+            "{PROTECTED_RESOURCE_METADATA_PATH}{}",
+            path.trim_end_matches('/')
+        )
+    }
+
+    /// The absolute URL of the metadata, as advertised in `WWW-Authenticate` challenges.
+    pub fn url(&self) -> String {
+        let (origin, _) = split_origin(&self.resource);
+        format!("{origin}{}", self.path())
+    }
+}
+
+/// Splits `https://host:port/path` into the origin and the path.
+fn split_origin(url: &str) -> (&str, &str) {
+    let authority = url.find("://").map_or(0, |i| i + 3);
+    match url[authority ..].find('/') {
+        Some(i) => url.split_at(authority + i),
+        None => (url, ""),
+    }
+}
+
+/// Why a request was refused. Turns into a `401` or `403` response with a `WWW-Authenticate`
+/// challenge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthRejection {
+    pub status: StatusCode,
+    pub challenge: String,
+    pub message: String,
+}
+
+impl IntoResponse for AuthRejection {
+    fn into_response(self) -> Response {
+        let mut response = (self.status, self.message).into_response();
+        if let Ok(challenge) = HeaderValue::from_str(&self.challenge) {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, challenge);
+        }
+        response
+    }
+}
+
+/// Requires a valid bearer token on every request of an HTTP transport.
+///
+/// Clones share the validator.
+#[derive(Clone)]
+pub struct BearerAuth {
+    validator: Arc<dyn TokenValidator>,
+    metadata: Option<Arc<ProtectedResourceMetadata>>,
+    required_scopes: Arc<[String]>,
+}
+
+impl BearerAuth {
+    pub fn new(validator: impl TokenValidator) -> Self {
+        Self {
+            validator: Arc::new(validator),
+            metadata: None,
+            required_scopes: Arc::new([]),
+        }
+    }
+
+    /// Advertises `metadata` in challenges, and serves it from [`BearerAuth::metadata_router`].
+    pub fn with_metadata(mut self, metadata: ProtectedResourceMetadata) -> Self {
+        self.metadata = Some(Arc::new(metadata));
+        self
+    }
+
+    /// Refuses tokens lacking any of these scopes with `403 insufficient_scope`.
+    pub fn with_required_scopes<I, S>(mut self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.required_scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Validates the bearer token in `headers`.
+    pub async fn authenticate(
+        &self,
+        headers: &HeaderMap,
+    ) -> std::result::Result<AuthIdentity, AuthRejection> {
+        let Some(token) = bearer_token(headers) else {
+            return Err(self.reject(StatusCode::UNAUTHORIZED, None, "Missing bearer token"));
+        };
+        let identity = match self.validator.validate(token).await {
+            Ok(identity) => identity,
+            Err(e) => {
+                tracing::debug!(error = %e, "Rejected bearer token");
+                return Err(self.reject(
+                    StatusCode::UNAUTHORIZED,
+                    Some("invalid_token"),
+                    &e.to_string(),
+                ));
+            }
+        };
+        if let Some(missing) = self
+            .required_scopes
+            .iter()
+            .find(|scope| !identity.has_scope(scope))
+        {
+            return Err(self.reject(
+                StatusCode::FORBIDDEN,
+                Some("insufficient_scope"),
+                &format!("Missing scope '{missing}'"),
+            ));
+        }
+        Ok(identity)
+    }
+
+    /// Checks that a later request of a session comes from the subject that opened it.
+    pub(crate) async fn authorize(
+        &self,
+        headers: &HeaderMap,
+        session: Option<&AuthIdentity>,
+    ) -> std::result::Result<AuthIdentity, AuthRejection> {
+        let identity = self.authenticate(headers).await?;
+        match session {
+            Some(session) if session.subject != identity.subject => Err(self.reject(
+                StatusCode::FORBIDDEN,
+                Some("invalid_token"),
+                "Token was issued to a different subject than the session's",
+            )),
+            _ => Ok(identity),
+        }
+    }
+
+    /// A router serving the protected resource metadata at [`ProtectedResourceMetadata::path`].
+    /// Merge it into the application at the root of the host. Serves nothing without metadata.
+    pub fn metadata_router(&self) -> Router {
+        let Some(metadata) = self.metadata.clone() else {
+            return Router::new();
+        };
+        let path = metadata.path();
+        Router::new().route(
+            &path,
+            get(move || async move { Json(metadata.as_ref().clone()) }),
+        )
+    }
+
+    fn reject(&self, status: StatusCode, error: Option<&str>, message: &str) -> AuthRejection {
+        let mut params = Vec::new();
+        if let Some(metadata) = &self.metadata {
+            params.push(format!("resource_metadata=\"{}\"", metadata.url()));
+        }
+        if let Some(error) = error {
+            params.push(format!("error=\"{error}\""));
+            params.push(format!(
+                "error_description=\"{}\"",
+                message.replace(['"', '\\'], "'")
+            ));
+        }
+        if status == StatusCode::FORBIDDEN && !self.required_scopes.is_empty() {
+            params.push(format!("scope=\"{}\"", self.required_scopes.join(" ")));
+        }
+        let challenge = match params.is_empty() {
+            true => "Bearer".to_string(),
+            false => format!("Bearer {}", params.join(", ")),
+        };
+        AuthRejection {
+            status,
+            challenge,
+            message: message.to_string(),
+        }
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    let token = token.trim();
+    (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty()).then_some(token)
+}
+
+#[cfg(feature = "jwt")]
+pub use jwt::JwtValidator;
+
+#[cfg(feature = "jwt")]
+mod jwt {
+    use std::str::FromStr;
+
+    use async_trait::async_trait;
+    use jsonwebtoken::{
+        Algorithm, DecodingKey, Validation, decode, decode_header,
+        jwk::{Jwk, JwkSet},
+    };
+    use serde_json::Value;
+
+    use super::TokenValidator;
+    use crate::{
+        error::{Error, Result},
+        service::context::AuthIdentity,
+    };
+
+    struct Key {
+        id: Option<String>,
+        key: DecodingKey,
+        /// The algorithm the key is for. When unknown, the token's own is used, which must still
+        /// belong to the key's family.
+        algorithm: Option<Algorithm>,
+    }
+
+    /// Validates JWT access tokens against locally configured keys.
+    ///
+    /// Tokens must be signed by one of the keys, picked by `kid` when the token names one, and
+    /// must not be expired. The issuer and audience are checked when configured. The subject comes
+    /// from `sub` and the scopes from `scope` (space-separated) or `scp`; every claim is kept in
+    /// [`AuthIdentity::claims`].
+    pub struct JwtValidator {
+        keys: Vec<Key>,
+        issuers: Vec<String>,
+        audiences: Vec<String>,
+        leeway: u64,
+    }
+
+    impl Default for JwtValidator {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl JwtValidator {
+        pub fn new() -> Self {
+            Self {
+                keys: Vec::new(),
+                issuers: Vec::new(),
+                audiences: Vec::new(),
+                leeway: 60,
+            }
+        }
+
+        /// Trusts every key of a JWKS document.
+        pub fn from_jwks(jwks: &JwkSet) -> Result<Self> {
+            jwks.keys
+                .iter()
+                .try_fold(Self::new(), |validator, jwk| validator.with_jwk(jwk))
+        }
+
+        /// Trusts the keys of a JWKS document given as JSON.
+        pub fn from_jwks_json(json: &str) -> Result<Self> {
+            Self::from_jwks(&serde_json::from_str(json)?)
+        }
+
+        pub fn with_jwk(mut self, jwk: &Jwk) -> Result<Self> {
+            let key = DecodingKey::from_jwk(jwk).map_err(|e| Error::System(e.to_string()))?;
+            let algorithm = jwk
+                .common
+                .key_algorithm
+                .and_then(|alg| Algorithm::from_str(&alg.to_string()).ok());
+            self.keys.push(Key {
+                id: jwk.common.key_id.clone(),
+                key,
+                algorithm,
+            });
+            Ok(self)
+        }
+
+        /// Trusts `key` for tokens signed with `algorithm`, whatever their `kid`.
+        pub fn with_key(mut self, key: DecodingKey, algorithm: Algorithm) -> Self {
+            self.keys.push(Key {
+                id: None,
+                key,
+                algorithm: Some(algorithm),
+            });
+            self
+        }
+
+        /// Only accepts tokens whose `iss` is one of the configured issuers.
+        pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+            self.issuers.push(issuer.into());
+            self
+        }
+
+        /// Only accepts tokens whose `aud` includes one of the configured audiences, normally
+        /// the URL of this server.
+        pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+            self.audiences.push(audience.into());
+            self
+        }
+
+        /// Seconds of clock skew tolerated when checking `exp` and `nbf`. Defaults to 60.
+        pub fn with_leeway(mut self, leeway: u64) -> Self {
+            self.leeway = leeway;
+            self
+        }
+
+        fn key(&self, kid: Option<&str>) -> Option<&Key> {
+            match kid {
+                Some(kid) => self
+                    .keys
+                    .iter()
+                    .find(|key| key.id.as_deref() == Some(kid))
+                    .or_else(|| self.keys.iter().find(|key| key.id.is_none())),
+                None => self.keys.first().filter(|_| self.keys.len() == 1),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl TokenValidator for JwtValidator {
+        async fn validate(&self, token: &str) -> Result<AuthIdentity> {
+            let unauthorized = |e: jsonwebtoken::errors::Error| Error::Unauthorized(e.to_string());
+            let header = decode_header(token).map_err(unauthorized)?;
+            let key = self
+                .key(header.kid.as_deref())
+                .ok_or_else(|| Error::Unauthorized("No key matches the token".to_string()))?;
+
+            let mut validation = Validation::new(key.algorithm.unwrap_or(header.alg));
+            validation.leeway = self.leeway;
+            validation.validate_nbf = true;
+            // Configured claims must also be present: jsonwebtoken only checks those it finds.
+            let mut required = vec!["exp"];
+            if !self.issuers.is_empty() {
+                validation.set_issuer(&self.issuers);
+                required.push("iss");
+            }
+            if self.audiences.is_empty() {
+                validation.validate_aud = false;
+            } else {
+                validation.set_audience(&self.audiences);
+                required.push("aud");
+            }
+            validation.set_required_spec_claims(&required);
+
+            let claims = decode::<Value>(token, &key.key, &validation)
+                .map_err(unauthorized)?
+                .claims;
+            let subject = claims
+                .get("sub")
+                .and_then(Value::as_str)
+                .ok_or_else(|| Error::Unauthorized("Token has no subject".to_string()))?;
+            let scopes: Vec<String> = match (claims.get("scope"), claims.get("scp")) {
+                (Some(Value::String(scope)), _) => {
+                    scope.split_whitespace().map(str::to_string).collect()
+                }
+                (_, Some(Value::Array(scp))) => scp
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect(),
+                (_, Some(Value::String(scp))) => {
+                    scp.split_whitespace().map(str::to_string).collect()
+                }
+                _ => Vec::new(),
+            };
+
+            Ok(AuthIdentity::new(subject)
+                .with_scopes(scopes)
+                .with_claims(claims))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "jwt"))]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, encode};
+    use serde_json::{Value, json};
+
+    use super::*;
+
+    const SECRET: &[u8] = b"test-secret";
+
+    fn token(claims: Value) -> String {
+        encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(SECRET),
+        )
+        .unwrap()
+    }
+
+    fn headers(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let value = format!("Bearer {token}");
+        headers.insert(header::AUTHORIZATION, value.parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_jwt_bearer_auth() {
+        let validator = JwtValidator::new()
+            .with_key(DecodingKey::from_secret(SECRET), Algorithm::HS256)
+            .with_issuer("https://auth.example.com")
+            .with_audience("https://mcp.example.com/mcp");
+        let metadata = ProtectedResourceMetadata::new("https://mcp.example.com/mcp")
+            .with_authorization_server("https://auth.example.com");
+        let auth = BearerAuth::new(validator)
+            .with_metadata(metadata.clone())
+            .with_required_scopes(["tools"]);
+        assert_eq!(
+            metadata.url(),
+            "https://mcp.example.com/.well-known/oauth-protected-resource/mcp"
+        );
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let claims = json!({
+            "sub": "alice",
+            "iss": "https://auth.example.com",
+            "aud": "https://mcp.example.com/mcp",
+            "exp": now + 60,
+            "scope": "tools read",
+        });
+        let identity = auth
+            .authenticate(&headers(&token(claims.clone())))
+            .await
+            .unwrap();
+        assert_eq!(identity.subject, "alice");
+        assert!(identity.has_scope("read"));
+
+        let missing = auth.authenticate(&HeaderMap::new()).await.unwrap_err();
+        assert_eq!(missing.status, StatusCode::UNAUTHORIZED);
+        assert!(missing.challenge.contains(&metadata.url()));
+
+        for (field, value) in [
+            ("aud", Some(json!("https://other.example.com"))),
+            ("aud", None),
+            ("iss", Some(json!("https://evil.example.com"))),
+            ("iss", None),
+            ("exp", Some(json!(now - 3600))),
+        ] {
+            let mut claims = claims.clone();
+            match value {
+                Some(value) => claims[field] = value,
+                None => {
+                    claims.as_object_mut().unwrap().remove(field);
+                }
+            }
+            let err = auth
+                .authenticate(&headers(&token(claims)))
+                .await
+                .unwrap_err();
+            assert_eq!(err.status, StatusCode::UNAUTHORIZED, "{field}");
+            assert!(err.challenge.contains("invalid_token"), "{field}");
+        }
+
+        let mut claims = claims;
+        claims["scope"] = json!("read");
+        let err = auth
+            .authenticate(&headers(&token(claims)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::FORBIDDEN);
+        assert!(err.challenge.contains("insufficient_scope"));
+    }
+}
@@ -1,27 +1,36 @@
 use std::{
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 
 use async_trait::async_trait;
-use futures::{Stream, stream::StreamExt};
-use pin_project::pin_project;
-use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use futures::{SinkExt, Stream, stream::StreamExt};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::Mutex,
+};
+use tokio_util::codec::{FramedRead, FramedWrite};
 
 use crate::{
     core::{protocol::message::JsonRpcMessage, utils::parse_json_rpc_message},
     error::{Error, Result},
-    transport::traits::ServerTransport,
+    transport::{
+        codec::{DEFAULT_MAX_MESSAGE_SIZE, Frame, FrameCodec, Framing},
+        traits::ServerTransport,
+    },
 };
 
-#[pin_project]
 /// A transport that reads and writes JSON-RPC messages over byte streams.
+///
+/// Messages are newline-delimited unless another [`Framing`] is chosen with
+/// [`ByteTransport::with_framing`]. An incoming message over the size limit is skipped and
+/// answered with an `Invalid Request` error; the connection stays usable.
 pub struct ByteTransport<R, W> {
-    #[pin]
-    reader: BufReader<R>,
-    #[pin]
-    writer: W,
-    buf: Vec<u8>,
+    reader: FramedRead<R, FrameCodec>,
+    writer: ByteWriter<W>,
+    framing: Framing,
+    max_message_size: usize,
 }
 
 impl<R, W> ByteTransport<R, W>
@@ -32,13 +41,75 @@ where
     /// Creates a new `ByteTransport` with the given reader and writer.
     pub fn new(reader: R, writer: W) -> Self {
         Self {
-            reader: BufReader::with_capacity(2 * 1024 * 1024, reader),
-            writer,
-            buf: Vec::with_capacity(2 * 1024 * 1024),
+            reader: FramedRead::new(reader, FrameCodec::default()),
+            writer: ByteWriter {
+                inner: Arc::new(Mutex::new(FramedWrite::new(writer, FrameCodec::default()))),
+            },
+            framing: Framing::default(),
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+        }
+    }
+
+    /// Sets how messages are delimited in both directions. Call it before the first read.
+    ///
+    /// # Panics
+    ///
+    /// If a handle from [`ByteTransport::writer`] is still alive, since messages already written
+    /// through it would use the old framing.
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        let codec = self.codec();
+        let writer = Arc::get_mut(&mut self.writer.inner)
+            .expect("with_framing must be called before ByteTransport::writer");
+        *writer.get_mut().encoder_mut() = codec.clone();
+        *self.reader.decoder_mut() = codec;
+        self
+    }
+
+    /// Sets the largest incoming message, in bytes.
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        *self.reader.decoder_mut() = self.codec();
+        self
+    }
+
+    /// A handle for writing messages while the transport is being read from.
+    pub fn writer(&self) -> ByteWriter<W> {
+        self.writer.clone()
+    }
+
+    fn codec(&self) -> FrameCodec {
+        FrameCodec::new(self.framing, self.max_message_size)
+    }
+}
+
+/// The write half of a [`ByteTransport`].
+///
+/// Clones share one underlying writer, so whole messages are written one at a time and never
+/// interleave.
+pub struct ByteWriter<W> {
+    inner: Arc<Mutex<FramedWrite<W, FrameCodec>>>,
+}
+
+impl<W> Clone for ByteWriter<W> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
         }
     }
 }
 
+impl<W> ByteWriter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    pub async fn write_message(&self, msg: &JsonRpcMessage) -> Result<()> {
+        let json = serde_json::to_vec(msg)?;
+        self.inner.lock().await.send(json.into()).await?;
+        Ok(())
+    }
+}
+
 impl<R, W> Stream for ByteTransport<R, W>
 where
     R: AsyncRead + Unpin,
@@ -46,29 +117,32 @@ where
 {
     type Item = Result<JsonRpcMessage>;
 
-    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let mut this = self.project();
-        this.buf.clear();
-
-        let mut reader = this.reader.as_mut();
-        let mut read_future = Box::pin(reader.read_until(b'\n', this.buf));
-        match read_future.as_mut().poll(cx) {
-            Poll::Ready(Ok(0)) => {
-                tracing::info!("Client closed connection (read 0 bytes)");
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let max_message_size = self.max_message_size;
+        match self.reader.poll_next_unpin(cx) {
+            Poll::Ready(None) => {
+                tracing::info!("Client closed connection");
                 Poll::Ready(None)
             }
-            Poll::Ready(Ok(_)) => {
-                let line = match String::from_utf8(std::mem::take(this.buf)) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        tracing::warn!(?e, "Invalid UTF-8 line");
-                        return Poll::Ready(Some(Err(Error::Utf8(e))));
-                    }
-                };
-                let rpc_message = parse_json_rpc_message(&line)?;
-                Poll::Ready(Some(Ok(rpc_message)))
+            Poll::Ready(Some(Ok(Frame::Message(frame)))) => {
+                let message = std::str::from_utf8(&frame)
+                    .map_err(|e| {
+                        tracing::warn!(?e, "Invalid UTF-8 message");
+                        Error::InvalidMessage(e.to_string())
+                    })
+                    .and_then(parse_json_rpc_message);
+                Poll::Ready(Some(message))
+            }
+            Poll::Ready(Some(Ok(Frame::Oversized))) => {
+                tracing::warn!(max_message_size, "Skipping oversized message");
+                Poll::Ready(Some(Err(Error::InvalidRequest {
+                    id: None,
+                    message: format!(
+                        "Message exceeds the maximum size of {max_message_size} bytes"
+                    ),
+                })))
             }
-            Poll::Ready(Err(e)) => Poll::Ready(Some(Err(Error::Io(e)))),
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(Error::Io(e)))),
             Poll::Pending => Poll::Pending,
         }
     }
@@ -85,11 +159,82 @@ where
     }
 
     async fn write_message(&mut self, msg: JsonRpcMessage) -> Result<()> {
-        let mut this = Pin::new(self).project();
-        let json = serde_json::to_string(&msg)?;
-        this.writer.write_all(json.as_bytes()).await?;
-        this.writer.write_all(b"\n").await?;
-        this.writer.flush().await?;
-        Ok(())
+        self.writer.write_message(&msg).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+    use super::*;
+    use crate::{server::Server, service::impls::counter::CounterRouter};
+
+    #[test]
+    #[should_panic(expected = "before ByteTransport::writer")]
+    fn test_framing_after_sharing_writer_panics() {
+        let (reader, writer) = tokio::io::split(tokio::io::duplex(64).0);
+        let transport = ByteTransport::new(reader, writer);
+        let _writer = transport.writer();
+        let _ = transport.with_framing(Framing::ContentLength);
+    }
+
+    #[tokio::test]
+    async fn test_content_length_framing_end_to_end() {
+        let (client, server) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(server);
+        let transport = ByteTransport::new(reader, writer).with_framing(Framing::ContentLength);
+        tokio::spawn(Server::new(Box::new(CounterRouter::new())).run(transport));
+
+        let (mut client_reader, mut client_writer) = tokio::io::split(client);
+        let body = serde_json::to_string_pretty(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "ping"
+        }))
+        .unwrap();
+        let request = format!("Content-Length: {}\r\n\r\n{body}", body.len());
+        client_writer.write_all(request.as_bytes()).await.unwrap();
+
+        let mut buf = vec![0; 4096];
+        let mut received = String::new();
+        let body = loop {
+            let n = client_reader.read(&mut buf).await.unwrap();
+            received.push_str(std::str::from_utf8(&buf[.. n]).unwrap());
+            if let Some((headers, body)) = received.split_once("\r\n\r\n") {
+                let length: usize = headers
+                    .strip_prefix("Content-Length: ")
+                    .unwrap()
+                    .parse()
+                    .unwrap();
+                if body.len() >= length {
+                    break body.to_string();
+                }
+            }
+        };
+        let response: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(response["id"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_message_is_rejected_and_skipped() {
+        let (client, server) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(server);
+        let transport = ByteTransport::new(reader, writer).with_max_message_size(64);
+        tokio::spawn(Server::new(Box::new(CounterRouter::new())).run(transport));
+
+        let (client_reader, mut client_writer) = tokio::io::split(client);
+        let ping = json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" });
+        let input = format!("{}\n{ping}\n", "x".repeat(200));
+        client_writer.write_all(input.as_bytes()).await.unwrap();
+
+        let mut lines = BufReader::new(client_reader).lines();
+        let error: Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(error["error"]["code"], -32600);
+        let response: Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(response["id"], 2);
     }
 }
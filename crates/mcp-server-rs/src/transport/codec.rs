@@ -0,0 +1,359 @@
+//! Framing for JSON-RPC messages on byte streams.
+//!
+//! The codecs only find message boundaries; they hand out raw bytes and leave parsing to the
+//! transport.
+
+use std::io;
+
+use tokio_util::{
+    bytes::{Buf, BufMut, Bytes, BytesMut},
+    codec::{Decoder, Encoder},
+};
+
+/// Largest message accepted when nothing else is configured.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
+
+const CONTENT_LENGTH: &str = "content-length";
+/// Longest header block accepted before a message body.
+const MAX_HEADER_SIZE: usize = 8 * 1024;
+
+/// How messages are delimited on a byte stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// One message per line, as used by MCP over stdio.
+    #[default]
+    Newline,
+    /// An LSP-style `Content-Length:` header block before each message, which allows
+    /// pretty-printed JSON.
+    ContentLength,
+}
+
+/// A decoded unit: a message, or notice that one exceeded the size limit and was skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Message(Bytes),
+    Oversized,
+}
+
+/// Splits on `\n`. A trailing `\r` is stripped and blank lines are skipped.
+///
+/// A line longer than `max_length` is reported as [`Frame::Oversized`] as soon as the limit is
+/// passed, and the rest of it is discarded without being buffered.
+#[derive(Debug, Clone)]
+pub struct NewlineCodec {
+    max_length: usize,
+    /// How far `decode` has already searched for a newline.
+    next_index: usize,
+    /// Whether the rest of an oversized line is being thrown away.
+    discarding: bool,
+}
+
+impl NewlineCodec {
+    pub fn new(max_length: usize) -> Self {
+        Self {
+            max_length,
+            next_index: 0,
+            discarding: false,
+        }
+    }
+}
+
+impl Default for NewlineCodec {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_MESSAGE_SIZE)
+    }
+}
+
+impl Decoder for NewlineCodec {
+    type Item = Frame;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Frame>> {
+        loop {
+            let Some(offset) = src[self.next_index ..].iter().position(|b| *b == b'\n') else {
+                if self.discarding {
+                    src.clear();
+                    self.next_index = 0;
+                } else if src.len() > self.max_length {
+                    src.clear();
+                    self.next_index = 0;
+                    self.discarding = true;
+                    return Ok(Some(Frame::Oversized));
+                } else {
+                    self.next_index = src.len();
+                }
+                return Ok(None);
+            };
+            let mut line = src.split_to(self.next_index + offset + 1);
+            self.next_index = 0;
+            if std::mem::take(&mut self.discarding) {
+                continue;
+            }
+
+            line.truncate(line.len() - 1);
+            if line.last() == Some(&b'\r') {
+                line.truncate(line.len() - 1);
+            }
+            if line.len() > self.max_length {
+                return Ok(Some(Frame::Oversized));
+            }
+            if !line.iter().all(u8::is_ascii_whitespace) {
+                return Ok(Some(Frame::Message(line.freeze())));
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> io::Result<Option<Frame>> {
+        if let Some(frame) = self.decode(src)? {
+            return Ok(Some(frame));
+        }
+        // A last message without a trailing newline still counts.
+        self.next_index = 0;
+        let rest = src.split();
+        if std::mem::take(&mut self.discarding) || rest.iter().all(u8::is_ascii_whitespace) {
+            return Ok(None);
+        }
+        Ok(Some(Frame::Message(rest.freeze())))
+    }
+}
+
+impl Encoder<Bytes> for NewlineCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Bytes, dst: &mut BytesMut) -> io::Result<()> {
+        dst.reserve(item.len() + 1);
+        dst.put(item);
+        dst.put_u8(b'\n');
+        Ok(())
+    }
+}
+
+/// Reads a header block terminated by an empty line, then exactly `Content-Length` bytes.
+///
+/// Header names are matched case-insensitively and headers other than `Content-Length` are
+/// ignored. Writes only a `Content-Length` header. A body longer than `max_length` is reported as
+/// [`Frame::Oversized`] and skipped, while a header block that never ends is an error.
+#[derive(Debug, Clone)]
+pub struct ContentLengthCodec {
+    max_length: usize,
+    /// The body length once the headers of the current message have been read.
+    content_length: Option<usize>,
+    /// Bytes of an oversized body still to be thrown away.
+    skip: usize,
+}
+
+impl ContentLengthCodec {
+    pub fn new(max_length: usize) -> Self {
+        Self {
+            max_length,
+            content_length: None,
+            skip: 0,
+        }
+    }
+
+    /// Parses the header block at the front of `src`, consuming it once it is complete.
+    fn decode_headers(src: &mut BytesMut) -> io::Result<Option<usize>> {
+        let Some(end) = src.windows(4).position(|w| w == b"\r\n\r\n") else {
+            if src.len() > MAX_HEADER_SIZE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Header block too large",
+                ));
+            }
+            return Ok(None);
+        };
+        let headers = src.split_to(end + 4);
+        let headers = std::str::from_utf8(&headers)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let mut content_length = None;
+        for line in headers.split("\r\n").filter(|line| !line.is_empty()) {
+            let (name, value) = line.split_once(':').ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Malformed header line: {line:?}"),
+                )
+            })?;
+            if name.trim().eq_ignore_ascii_case(CONTENT_LENGTH) {
+                let length = value.trim().parse().map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid Content-Length: {:?}", value.trim()),
+                    )
+                })?;
+                content_length = Some(length);
+            }
+        }
+
+        content_length.map(Some).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length header")
+        })
+    }
+
+    fn skip(&mut self, src: &mut BytesMut) {
+        let n = self.skip.min(src.len());
+        src.advance(n);
+        self.skip -= n;
+    }
+}
+
+impl Default for ContentLengthCodec {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_MESSAGE_SIZE)
+    }
+}
+
+impl Decoder for ContentLengthCodec {
+    type Item = Frame;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Frame>> {
+        self.skip(src);
+        if self.skip > 0 {
+            return Ok(None);
+        }
+
+        let length = match self.content_length {
+            Some(length) => length,
+            None => match Self::decode_headers(src)? {
+                Some(length) if length > self.max_length => {
+                    self.skip = length;
+                    self.skip(src);
+                    return Ok(Some(Frame::Oversized));
+                }
+                Some(length) => {
+                    self.content_length = Some(length);
+                    length
+                }
+                None => return Ok(None),
+            },
+        };
+
+        if src.len() < length {
+            src.reserve(length - src.len());
+            return Ok(None);
+        }
+        self.content_length = None;
+        Ok(Some(Frame::Message(src.split_to(length).freeze())))
+    }
+}
+
+impl Encoder<Bytes> for ContentLengthCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Bytes, dst: &mut BytesMut) -> io::Result<()> {
+        let header = format!("Content-Length: {}\r\n\r\n", item.len());
+        dst.reserve(header.len() + item.len());
+        dst.put(header.as_bytes());
+        dst.put(item);
+        Ok(())
+    }
+}
+
+/// One of the codecs above, picked by [`Framing`].
+#[derive(Debug, Clone)]
+pub enum FrameCodec {
+    Newline(NewlineCodec),
+    ContentLength(ContentLengthCodec),
+}
+
+impl FrameCodec {
+    pub fn new(framing: Framing, max_length: usize) -> Self {
+        match framing {
+            Framing::Newline => Self::Newline(NewlineCodec::new(max_length)),
+            Framing::ContentLength => Self::ContentLength(ContentLengthCodec::new(max_length)),
+        }
+    }
+}
+
+impl Default for FrameCodec {
+    fn default() -> Self {
+        Self::new(Framing::default(), DEFAULT_MAX_MESSAGE_SIZE)
+    }
+}
+
+impl Decoder for FrameCodec {
+    type Item = Frame;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Frame>> {
+        match self {
+            Self::Newline(codec) => codec.decode(src),
+            Self::ContentLength(codec) => codec.decode(src),
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> io::Result<Option<Frame>> {
+        match self {
+            Self::Newline(codec) => codec.decode_eof(src),
+            Self::ContentLength(codec) => codec.decode_eof(src),
+        }
+    }
+}
+
+impl Encoder<Bytes> for FrameCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Bytes, dst: &mut BytesMut) -> io::Result<()> {
+        match self {
+            Self::Newline(codec) => codec.encode(item, dst),
+            Self::ContentLength(codec) => codec.encode(item, dst),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_length_codec_round_trip_in_pieces() {
+        let body = Bytes::from_static(b"{\n  \"jsonrpc\": \"2.0\",\n  \"method\": \"ping\"\n}");
+        let mut encoded = BytesMut::new();
+        let mut codec = ContentLengthCodec::default();
+        codec.encode(body.clone(), &mut encoded).unwrap();
+        codec.encode(body.clone(), &mut encoded).unwrap();
+
+        // Feed one byte at a time to exercise partial headers and bodies.
+        let mut src = BytesMut::new();
+        let mut frames = Vec::new();
+        for byte in encoded {
+            src.put_u8(byte);
+            if let Some(frame) = codec.decode(&mut src).unwrap() {
+                frames.push(frame);
+            }
+        }
+        assert_eq!(
+            frames,
+            vec![Frame::Message(body.clone()), Frame::Message(body)]
+        );
+
+        let mut src = BytesMut::from("content-type: application/json\r\n\r\n{}");
+        assert!(codec.decode(&mut src).is_err());
+
+        let mut codec = ContentLengthCodec::new(4);
+        let mut src =
+            BytesMut::from("Content-Length: 10\r\n\r\n0123456789Content-Length: 2\r\n\r\n{}");
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(Frame::Oversized));
+        assert_eq!(
+            codec.decode(&mut src).unwrap(),
+            Some(Frame::Message(Bytes::from_static(b"{}")))
+        );
+    }
+
+    #[test]
+    fn test_newline_codec_skips_blank_and_oversized_lines() {
+        let message = |s: &'static str| Some(Frame::Message(Bytes::from_static(s.as_bytes())));
+        let mut codec = NewlineCodec::new(8);
+
+        let mut src = BytesMut::from("{\"a\":1}\r\n\n0123456789");
+        assert_eq!(codec.decode(&mut src).unwrap(), message("{\"a\":1}"));
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(Frame::Oversized));
+        assert!(src.is_empty());
+
+        // The tail of the oversized line is dropped; what follows it is decoded again.
+        src.extend_from_slice(b"abcdef\n{\"b\":2}");
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        assert_eq!(codec.decode_eof(&mut src).unwrap(), message("{\"b\":2}"));
+    }
+}
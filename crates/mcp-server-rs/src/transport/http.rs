@@ -0,0 +1,30 @@
+//! Helpers shared by the HTTP-based transports.
+
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    core::protocol::{
+        constants::JSONRPC_EXPECTED_VERSION,
+        error::ErrorData,
+        message::{JsonRpcError, JsonRpcMessage},
+    },
+    error::Error,
+};
+
+/// A JSON-RPC error body for a message that never reached a session.
+pub(crate) fn rpc_error(status: StatusCode, e: &Error) -> Response {
+    let id = match e {
+        Error::InvalidRequest { id, .. } => *id,
+        _ => None,
+    };
+    let body = JsonRpcMessage::Error(JsonRpcError {
+        jsonrpc: JSONRPC_EXPECTED_VERSION.to_string(),
+        id,
+        error: ErrorData::from(e),
+    });
+    (status, Json(body)).into_response()
+}
@@ -1,5 +1,26 @@
+#[cfg(any(feature = "streamable-http", feature = "sse-server"))]
+pub mod auth;
 pub mod byte;
+pub mod codec;
+#[cfg(any(feature = "streamable-http", feature = "sse-server"))]
+mod http;
 pub mod sse;
+#[cfg(feature = "sse-server")]
+pub mod sse_server;
+pub mod stdio;
+#[cfg(feature = "streamable-http")]
+pub mod streamable_http;
 pub mod traits;
+#[cfg(unix)]
+pub mod unix;
+#[cfg(feature = "websocket")]
+pub mod websocket;
 
 pub use byte::ByteTransport;
+pub use codec::Framing;
+pub use stdio::StdioTransport;
+
+/// A random, unguessable session id.
+pub(crate) fn new_session_id() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
@@ -1,35 +1,46 @@
-use std::io;
-
 use async_trait::async_trait;
-use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 
 use crate::{
-    core::protocol::message::JsonRpcMessage,
-    error::{Error, Result},
+    core::{
+        protocol::message::JsonRpcMessage,
+        utils::{QueueReceiver, QueueSender, QueueStats},
+    },
+    error::Result,
     transport::traits::ServerTransport,
 };
 
-/// A transport that wraps an SSE-style message sender
+/// A transport that wraps an SSE-style message sender.
+///
+/// Both directions are bounded queues made with
+/// [`message_queue`](crate::core::utils::message_queue); their
+/// [`QueueConfig`](crate::core::utils::QueueConfig) decides what happens when the other side falls
+/// behind.
 pub struct SseTransport {
-    sender: UnboundedSender<JsonRpcMessage>,
-    receiver: UnboundedReceiver<JsonRpcMessage>,
+    sender: QueueSender,
+    receiver: QueueReceiver,
 }
 
 impl SseTransport {
-    pub fn new(
-        sender: UnboundedSender<JsonRpcMessage>,
-        receiver: UnboundedReceiver<JsonRpcMessage>,
-    ) -> Self {
+    /// `sender` carries messages to the client, `receiver` brings them from it.
+    pub fn new(sender: QueueSender, receiver: QueueReceiver) -> Self {
         Self { sender, receiver }
     }
+
+    /// Depth of the queue towards the client.
+    pub fn outbound_stats(&self) -> QueueStats {
+        self.sender.stats()
+    }
+
+    /// Depth of the queue from the client.
+    pub fn inbound_stats(&self) -> QueueStats {
+        self.receiver.stats()
+    }
 }
 
 #[async_trait]
 impl ServerTransport for SseTransport {
     async fn write_message(&mut self, msg: JsonRpcMessage) -> Result<()> {
-        self.sender
-            .send(msg)
-            .map_err(|_| Error::Io(io::Error::new(io::ErrorKind::BrokenPipe, "SSE send failed")))
+        self.sender.send(msg).await
     }
 
     async fn read_message(&mut self) -> Option<Result<JsonRpcMessage>> {
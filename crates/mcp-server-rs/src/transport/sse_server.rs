@@ -0,0 +1,855 @@
+//! HTTP+SSE server transport (MCP 2024-11-05).
+//!
+//! A client opens an SSE stream with `GET`. The first event, `endpoint`, tells it where to
+//! `POST` its messages. Everything the server sends comes back over the stream.
+//!
+//! Every message event carries an id. A client whose stream drops can reconnect with the last id
+//! it saw in `Last-Event-ID` and resumes the same session: missed events are replayed from a
+//! bounded per-session buffer.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use axum::{
+    Router,
+    body::Bytes,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{
+        IntoResponse, Response, Sse,
+        sse::{Event, KeepAlive},
+    },
+    routing::{get, post},
+};
+use futures::{
+    StreamExt,
+    future::{self, BoxFuture},
+    stream,
+};
+use serde::Deserialize;
+use tokio::sync::{Notify, RwLock, mpsc};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    core::{
+        protocol::message::JsonRpcMessage,
+        utils::{OverflowPolicy, QueueStats, parse_json_rpc_message},
+    },
+    error::{Error, Result},
+    metrics::Metrics,
+    server::Server,
+    service::context::AuthIdentity,
+    shutdown::ShutdownCoordinator,
+    transport::{
+        auth::{AuthRejection, BearerAuth},
+        http::rpc_error,
+        new_session_id,
+        traits::ServerTransport,
+    },
+};
+
+/// Messages buffered per direction and session when nothing else is configured.
+pub const DEFAULT_QUEUE_CAPACITY: usize = 64;
+
+/// Delivered events kept per session for replay when nothing else is configured.
+pub const DEFAULT_REPLAY_CAPACITY: usize = 128;
+
+/// How long a session outlives its stream when nothing else is configured.
+pub const DEFAULT_RESUME_WINDOW: Duration = Duration::from_secs(30);
+
+const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+type ServerFactory = Arc<dyn Fn() -> BoxFuture<'static, Result<Server>> + Send + Sync>;
+type Sessions = Arc<RwLock<HashMap<String, Arc<Session>>>>;
+
+/// Serves MCP sessions over HTTP+SSE, running one [`Server`] per session.
+#[derive(Clone)]
+pub struct SseServer {
+    factory: ServerFactory,
+    sessions: Sessions,
+    sse_path: String,
+    post_path: String,
+    queue_capacity: usize,
+    overflow_policy: OverflowPolicy,
+    replay_capacity: usize,
+    resume_window: Duration,
+    auth: Option<BearerAuth>,
+    shutdown: ShutdownCoordinator,
+}
+
+impl SseServer {
+    /// `factory` builds the server for each new session. A failing factory answers the
+    /// connection with `500 Internal Server Error`.
+    pub fn new<F, Fut>(factory: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Server>> + Send + 'static,
+    {
+        Self {
+            factory: Arc::new(move || Box::pin(factory())),
+            sessions: Default::default(),
+            sse_path: "/sse".to_string(),
+            post_path: "/message".to_string(),
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            overflow_policy: OverflowPolicy::default(),
+            replay_capacity: DEFAULT_REPLAY_CAPACITY,
+            resume_window: DEFAULT_RESUME_WINDOW,
+            auth: None,
+            shutdown: ShutdownCoordinator::new(),
+        }
+    }
+
+    /// Sets the paths of the SSE stream and the message endpoint. Both must be in the same
+    /// directory, since the endpoint is announced relative to the stream.
+    pub fn with_paths(mut self, sse_path: impl Into<String>, post_path: impl Into<String>) -> Self {
+        self.sse_path = sse_path.into();
+        self.post_path = post_path.into();
+        self
+    }
+
+    /// Sets how many messages may wait per direction before a session pushes back.
+    pub fn with_queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = capacity.max(1);
+        self
+    }
+
+    /// Sets what a session does when its client falls `queue_capacity` messages behind. With
+    /// the default, [`OverflowPolicy::Block`], the session waits for the client.
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
+    /// Sets how many already delivered events each session keeps for replay.
+    pub fn with_replay_capacity(mut self, capacity: usize) -> Self {
+        self.replay_capacity = capacity;
+        self
+    }
+
+    /// Sets how long a session waits for its client to reconnect before it is closed.
+    pub fn with_resume_window(mut self, window: Duration) -> Self {
+        self.resume_window = window;
+        self
+    }
+
+    /// Requires a valid bearer token to open a stream and on every message. The identity of the
+    /// request that opens a session is what its handlers see; later requests, including
+    /// reconnects, must come from the same subject.
+    pub fn with_auth(mut self, auth: BearerAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Runs sessions under `shutdown`. Once it shuts down, new sessions and requests are refused
+    /// and every session drains and closes, ending its stream.
+    pub fn with_shutdown(mut self, shutdown: ShutdownCoordinator) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Reports the queues of all sessions in `metrics`, summed, as `sse_inbound` and
+    /// `sse_outbound`.
+    pub fn with_metrics(self, metrics: Metrics) -> Self {
+        let sessions = self.sessions.clone();
+        metrics.register_queue("sse_inbound", move || {
+            queue_stats(&sessions, |session| QueueStats {
+                depth: session.inbound.max_capacity() - session.inbound.capacity(),
+                capacity: session.inbound.max_capacity(),
+                ..Default::default()
+            })
+        });
+        let sessions = self.sessions.clone();
+        metrics.register_queue("sse_outbound", move || {
+            queue_stats(&sessions, |session| {
+                let log = session.log.lock().unwrap();
+                QueueStats {
+                    depth: log.undelivered(),
+                    capacity: session.inbound.max_capacity(),
+                    dropped: log.dropped,
+                    ..Default::default()
+                }
+            })
+        });
+        self
+    }
+
+    /// A router serving the SSE stream and the message endpoint. It can be nested into a larger
+    /// app.
+    pub fn router(self) -> Router {
+        Router::new()
+            .route(&self.sse_path, get(handle_sse))
+            .route(&self.post_path, post(handle_post))
+            .with_state(self)
+    }
+
+    pub async fn session_count(&self) -> usize {
+        self.sessions.read().await.len()
+    }
+
+    fn endpoint(&self, session_id: &str) -> String {
+        let path = self.post_path.rsplit('/').next().unwrap_or_default();
+        format!("{path}?sessionId={session_id}")
+    }
+
+    /// Finds the session a `Last-Event-ID` belongs to, along with the sequence number in it.
+    async fn resumable(&self, headers: &HeaderMap) -> Option<(Arc<Session>, u64)> {
+        let last_event_id = headers.get(LAST_EVENT_ID_HEADER)?.to_str().ok()?;
+        let (session_id, seq) = last_event_id.rsplit_once('-')?;
+        let seq = seq.parse().ok()?;
+        let session = self.sessions.read().await.get(session_id).cloned();
+        if session.is_none() {
+            tracing::info!(%session_id, "Cannot resume unknown session, starting a new one");
+        }
+        Some((session?, seq))
+    }
+
+    /// Checks the request's bearer token, which must belong to the subject of `session` if
+    /// given. Always succeeds without authentication configured.
+    async fn authorize(
+        &self,
+        headers: &HeaderMap,
+        session: Option<&Session>,
+    ) -> std::result::Result<Option<AuthIdentity>, AuthRejection> {
+        let Some(auth) = &self.auth else {
+            return Ok(None);
+        };
+        auth.authorize(headers, session.and_then(|s| s.auth.as_ref()))
+            .await
+            .map(Some)
+    }
+}
+
+/// Sums a queue over every session. A scrape never waits for the session map; while it is being
+/// written to, the queues are reported empty.
+fn queue_stats(sessions: &Sessions, stats: impl Fn(&Session) -> QueueStats) -> QueueStats {
+    let Ok(sessions) = sessions.try_read() else {
+        return QueueStats::default();
+    };
+    sessions
+        .values()
+        .fold(QueueStats::default(), |mut total, session| {
+            let session = stats(session);
+            total.depth += session.depth;
+            total.capacity += session.capacity;
+            total.dropped += session.dropped;
+            total
+        })
+}
+
+/// State shared between a session's [`Server`] and whichever stream is attached to it.
+struct Session {
+    id: String,
+    inbound: mpsc::Sender<JsonRpcMessage>,
+    log: Mutex<EventLog>,
+    /// Signalled whenever the log changes.
+    changed: Notify,
+    /// Ends the session once its client has been gone longer than the resume window.
+    expired: CancellationToken,
+    /// The caller that opened the session.
+    auth: Option<AuthIdentity>,
+}
+
+/// Outgoing messages numbered in order, and how far the attached stream has got.
+struct EventLog {
+    events: VecDeque<(u64, JsonRpcMessage)>,
+    next_seq: u64,
+    /// The last sequence number handed to a stream.
+    delivered: u64,
+    /// Bumped for every new stream, so that a superseded stream knows to stop.
+    connection: u64,
+    attached: bool,
+    closed: bool,
+    /// Notifications evicted under [`OverflowPolicy::DropOldest`].
+    dropped: u64,
+}
+
+impl EventLog {
+    /// Events not yet handed to a stream.
+    fn undelivered(&self) -> usize {
+        let delivered = self.delivered;
+        self.events.len() - self.events.partition_point(|(seq, _)| *seq <= delivered)
+    }
+
+    /// Evicts the oldest undelivered notification, if there is one. Requests and responses are
+    /// never evicted.
+    fn evict_notification(&mut self) {
+        let delivered = self.delivered;
+        let oldest = self.events.iter().position(|(seq, msg)| {
+            *seq > delivered && matches!(msg, JsonRpcMessage::Notification(_))
+        });
+        if let Some(oldest) = oldest {
+            self.events.remove(oldest);
+            self.dropped += 1;
+        }
+    }
+}
+
+impl Session {
+    /// Registers a new stream that continues after `cursor`, returning its connection number
+    /// and the cursor, clamped to what has actually been sent.
+    fn attach(&self, cursor: u64) -> (u64, u64) {
+        let attached = {
+            let mut log = self.log.lock().unwrap();
+            let cursor = cursor.min(log.next_seq - 1);
+            if log
+                .events
+                .front()
+                .is_some_and(|(oldest, _)| *oldest > cursor + 1)
+            {
+                tracing::warn!(
+                    session_id = %self.id,
+                    cursor,
+                    "Events since the client's last event are no longer buffered"
+                );
+            }
+            log.connection += 1;
+            log.attached = true;
+            (log.connection, cursor)
+        };
+        // Wakes the previous stream, if any, so it sees it has been replaced.
+        self.changed.notify_waiters();
+        attached
+    }
+}
+
+/// The [`ServerTransport`] a session's [`Server`] runs on. Both directions are bounded, so a
+/// slow client slows the server down instead of growing memory.
+struct SseSessionTransport {
+    session: Arc<Session>,
+    inbound: mpsc::Receiver<JsonRpcMessage>,
+    queue_capacity: usize,
+    overflow_policy: OverflowPolicy,
+    replay_capacity: usize,
+}
+
+#[async_trait]
+impl ServerTransport for SseSessionTransport {
+    async fn read_message(&mut self) -> Option<Result<JsonRpcMessage>> {
+        self.inbound.recv().await.map(Ok)
+    }
+
+    async fn write_message(&mut self, msg: JsonRpcMessage) -> Result<()> {
+        let session = &self.session;
+        let mut msg = Some(msg);
+        loop {
+            let changed = session.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            {
+                let mut log = session.log.lock().unwrap();
+                if log.closed {
+                    return Err(Error::ChannelClosed);
+                }
+                if log.undelivered() >= self.queue_capacity {
+                    match self.overflow_policy {
+                        OverflowPolicy::Block => {}
+                        OverflowPolicy::DropOldest => log.evict_notification(),
+                        OverflowPolicy::Disconnect => {
+                            tracing::warn!(
+                                session_id = %session.id,
+                                "Client too slow, closing session"
+                            );
+                            log.closed = true;
+                            drop(log);
+                            session.changed.notify_waiters();
+                            return Err(Error::QueueFull);
+                        }
+                    }
+                }
+                if log.undelivered() < self.queue_capacity {
+                    let seq = log.next_seq;
+                    log.next_seq += 1;
+                    log.events.extend(msg.take().map(|msg| (seq, msg)));
+                    while log.events.len() > self.replay_capacity
+                        && log
+                            .events
+                            .front()
+                            .is_some_and(|(seq, _)| *seq <= log.delivered)
+                    {
+                        log.events.pop_front();
+                    }
+                    drop(log);
+                    session.changed.notify_waiters();
+                    return Ok(());
+                }
+            }
+
+            tokio::select! {
+                _ = &mut changed => {}
+                _ = session.expired.cancelled() => return Err(Error::ChannelClosed),
+            }
+        }
+    }
+
+    /// Ends the attached stream once it has delivered what was already written.
+    async fn close(&mut self) -> Result<()> {
+        self.session.log.lock().unwrap().closed = true;
+        self.session.changed.notify_waiters();
+        Ok(())
+    }
+
+    fn session_id(&self) -> Option<String> {
+        Some(self.session.id.clone())
+    }
+
+    fn auth_identity(&self) -> Option<AuthIdentity> {
+        self.session.auth.clone()
+    }
+}
+
+/// One SSE connection's view of a session.
+struct SessionStream {
+    session: Arc<Session>,
+    connection: u64,
+    cursor: u64,
+    resume_window: Duration,
+}
+
+impl SessionStream {
+    /// Waits for the next event after the cursor. Ends when the session closes or a newer
+    /// connection takes over.
+    async fn next_event(&mut self) -> Option<Event> {
+        loop {
+            let changed = self.session.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            {
+                let mut log = self.session.log.lock().unwrap();
+                if log.connection != self.connection {
+                    return None;
+                }
+                let next = log.events.partition_point(|(seq, _)| *seq <= self.cursor);
+                if let Some((seq, msg)) = log.events.get(next).cloned() {
+                    self.cursor = seq;
+                    log.delivered = log.delivered.max(seq);
+                    drop(log);
+                    self.session.changed.notify_waiters();
+                    return Some(
+                        Event::default()
+                            .id(format!("{}-{seq}", self.session.id))
+                            .event("message")
+                            .json_data(msg)
+                            .unwrap_or_else(|e| Event::default().comment(e.to_string())),
+                    );
+                }
+                if log.closed {
+                    return None;
+                }
+            }
+            changed.await;
+        }
+    }
+}
+
+impl Drop for SessionStream {
+    fn drop(&mut self) {
+        {
+            let mut log = self.session.log.lock().unwrap();
+            if log.connection != self.connection || log.closed {
+                return;
+            }
+            log.attached = false;
+        }
+        tracing::info!(session_id = %self.session.id, "Client disconnected");
+
+        // Give the client a chance to reconnect before the session is torn down.
+        let session = self.session.clone();
+        let connection = self.connection;
+        let window = self.resume_window;
+        tokio::spawn(async move {
+            tokio::time::sleep(window).await;
+            let expired = {
+                let log = session.log.lock().unwrap();
+                !log.attached && log.connection == connection
+            };
+            if expired {
+                session.expired.cancel();
+            }
+        });
+    }
+}
+
+async fn handle_sse(State(server): State<SseServer>, headers: HeaderMap) -> Response {
+    let resumed = server.resumable(&headers).await;
+    let auth = match server
+        .authorize(
+            &headers,
+            resumed.as_ref().map(|(session, _)| session.as_ref()),
+        )
+        .await
+    {
+        Ok(auth) => auth,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let (session, cursor) = match resumed {
+        Some((session, cursor)) => {
+            tracing::info!(session_id = %session.id, cursor, "SSE session resumed");
+            (session, cursor)
+        }
+        None if server.shutdown.is_shutting_down() => {
+            return (StatusCode::SERVICE_UNAVAILABLE, "Server is shutting down").into_response();
+        }
+        None => match start_session(&server, auth).await {
+            Ok(session) => (session, 0),
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to create server for SSE connection");
+                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to start session")
+                    .into_response();
+            }
+        },
+    };
+
+    let endpoint = Event::default()
+        .event("endpoint")
+        .data(server.endpoint(&session.id));
+    let (connection, cursor) = session.attach(cursor);
+    let stream = SessionStream {
+        session,
+        connection,
+        cursor,
+        resume_window: server.resume_window,
+    };
+    let messages = stream::unfold(stream, |mut stream| async move {
+        let event = stream.next_event().await?;
+        Some((Ok(event), stream))
+    });
+    let events = stream::once(future::ready(Ok::<_, Infallible>(endpoint))).chain(messages);
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Creates a session and spawns its [`Server`].
+async fn start_session(server: &SseServer, auth: Option<AuthIdentity>) -> Result<Arc<Session>> {
+    let runner = (server.factory)().await?;
+    let (inbound_tx, inbound_rx) = mpsc::channel(server.queue_capacity);
+    let session = Arc::new(Session {
+        id: new_session_id(),
+        inbound: inbound_tx,
+        log: Mutex::new(EventLog {
+            events: VecDeque::new(),
+            next_seq: 1,
+            delivered: 0,
+            connection: 0,
+            attached: false,
+            closed: false,
+            dropped: 0,
+        }),
+        changed: Notify::new(),
+        expired: CancellationToken::new(),
+        auth,
+    });
+    server
+        .sessions
+        .write()
+        .await
+        .insert(session.id.clone(), session.clone());
+
+    let transport = SseSessionTransport {
+        session: session.clone(),
+        inbound: inbound_rx,
+        queue_capacity: server.queue_capacity,
+        overflow_policy: server.overflow_policy,
+        replay_capacity: server.replay_capacity,
+    };
+    let sessions = server.sessions.clone();
+    let task_session = session.clone();
+    let shutdown = server.shutdown.cancelled();
+    server.shutdown.track(async move {
+        let session_id = &task_session.id;
+        let result = tokio::select! {
+            result = runner.run_until(transport, shutdown) => result,
+            _ = task_session.expired.cancelled() => {
+                tracing::info!(%session_id, "Client did not reconnect in time");
+                Ok(())
+            }
+        };
+        sessions.write().await.remove(session_id);
+        task_session.log.lock().unwrap().closed = true;
+        task_session.changed.notify_waiters();
+        tracing::info!(%session_id, "Session closed");
+
+        if let Err(e) = result {
+            tracing::error!(%session_id, error = %e, "Session ended with an error");
+        }
+    });
+    tracing::info!(session_id = %session.id, "SSE session created");
+
+    Ok(session)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PostQuery {
+    session_id: Option<String>,
+}
+
+async fn handle_post(
+    State(server): State<SseServer>,
+    Query(query): Query<PostQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(session_id) = query.session_id else {
+        return (StatusCode::BAD_REQUEST, "Missing sessionId").into_response();
+    };
+    let Some(session) = server.sessions.read().await.get(&session_id).cloned() else {
+        return (StatusCode::NOT_FOUND, "Session not found").into_response();
+    };
+    if let Err(rejection) = server.authorize(&headers, Some(&session)).await {
+        return rejection.into_response();
+    }
+
+    let is_json = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(';').next().unwrap_or_default().trim() == "application/json");
+    if !is_json {
+        return (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "Content-Type must be application/json",
+        )
+            .into_response();
+    }
+
+    let msg = match std::str::from_utf8(&body)
+        .map_err(|e| Error::InvalidMessage(e.to_string()))
+        .and_then(parse_json_rpc_message)
+    {
+        Ok(msg) => msg,
+        Err(e) => return rpc_error(StatusCode::BAD_REQUEST, &e),
+    };
+    if matches!(msg, JsonRpcMessage::Request(_)) && server.shutdown.is_shutting_down() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Server is shutting down").into_response();
+    }
+
+    match session.inbound.try_send(msg) {
+        Ok(()) => StatusCode::ACCEPTED.into_response(),
+        Err(mpsc::error::TrySendError::Full(_)) => {
+            tracing::warn!(%session_id, "Session queue full, rejecting message");
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                [(header::RETRY_AFTER, "1")],
+                "Session is busy",
+            )
+                .into_response()
+        }
+        Err(mpsc::error::TrySendError::Closed(_)) => {
+            (StatusCode::GONE, "Session has ended").into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use http_body_util::BodyExt;
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::service::impls::counter::CounterRouter;
+
+    fn detached_transport(policy: OverflowPolicy) -> SseSessionTransport {
+        let (inbound_tx, inbound) = mpsc::channel(2);
+        let session = Arc::new(Session {
+            id: "s1".into(),
+            inbound: inbound_tx,
+            log: Mutex::new(EventLog {
+                events: VecDeque::new(),
+                next_seq: 1,
+                delivered: 0,
+                connection: 0,
+                attached: false,
+                closed: false,
+                dropped: 0,
+            }),
+            changed: Notify::new(),
+            expired: CancellationToken::new(),
+            auth: None,
+        });
+        SseSessionTransport {
+            session,
+            inbound,
+            queue_capacity: 2,
+            overflow_policy: policy,
+            replay_capacity: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_overflow_policies() {
+        let notification = |method: &str| {
+            JsonRpcMessage::Notification(crate::core::protocol::message::JsonRpcNotification::new(
+                method, None,
+            ))
+        };
+
+        let mut transport = detached_transport(OverflowPolicy::DropOldest);
+        for method in ["a", "b", "c"] {
+            transport.write_message(notification(method)).await.unwrap();
+        }
+        {
+            let log = transport.session.log.lock().unwrap();
+            let methods: Vec<_> = log
+                .events
+                .iter()
+                .map(|(_, msg)| match msg {
+                    JsonRpcMessage::Notification(n) => n.method.as_str(),
+                    _ => unreachable!(),
+                })
+                .collect();
+            assert_eq!(methods, ["b", "c"]);
+            assert_eq!(log.dropped, 1);
+        }
+
+        let mut transport = detached_transport(OverflowPolicy::Disconnect);
+        for method in ["a", "b"] {
+            transport.write_message(notification(method)).await.unwrap();
+        }
+        let err = transport.write_message(notification("c")).await;
+        assert!(matches!(err, Err(Error::QueueFull)));
+        assert!(transport.session.log.lock().unwrap().closed);
+    }
+
+    fn post(uri: &str, content_type: &str, body: &str) -> axum::http::Request<Body> {
+        axum::http::Request::post(uri)
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    /// Reads from the SSE body until `needle` shows up, returning everything read so far.
+    async fn read_until(body: &mut Body, needle: &str) -> String {
+        let mut text = String::new();
+        while !text.contains(needle) {
+            let frame = body.frame().await.unwrap().unwrap();
+            if let Ok(data) = frame.into_data() {
+                text.push_str(std::str::from_utf8(&data).unwrap());
+            }
+        }
+        text
+    }
+
+    #[tokio::test]
+    async fn test_sse_session_round_trip() {
+        let server = SseServer::new(|| async { Ok(Server::new(Box::new(CounterRouter::new()))) })
+            .with_resume_window(Duration::ZERO);
+        let router = server.clone().router();
+
+        let response = router
+            .clone()
+            .oneshot(
+                axum::http::Request::get("/sse")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut body = response.into_body();
+
+        let text = read_until(&mut body, "sessionId=").await;
+        let endpoint = text
+            .lines()
+            .find_map(|line| line.strip_prefix("data: "))
+            .unwrap()
+            .to_string();
+        assert!(endpoint.starts_with("message?sessionId="));
+        let uri = format!("/{endpoint}");
+
+        let ping = json!({ "jsonrpc": "2.0", "id": 7, "method": "ping" }).to_string();
+        let response = router
+            .clone()
+            .oneshot(post(&uri, "text/plain", &ping))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let response = router
+            .clone()
+            .oneshot(post(&uri, "application/json", "not json"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = router
+            .clone()
+            .oneshot(post(&uri, "application/json", &ping))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        read_until(&mut body, r#""id":7"#).await;
+
+        // Dropping the stream ends the session once the resume window has passed.
+        drop(body);
+        for _ in 0 .. 50 {
+            if server.session_count().await == 0 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(server.session_count().await, 0);
+
+        let response = router
+            .oneshot(post(&uri, "application/json", &ping))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_replays_missed_events() {
+        let router =
+            SseServer::new(|| async { Ok(Server::new(Box::new(CounterRouter::new()))) }).router();
+        let open = |last_event_id: Option<String>| {
+            let mut request = axum::http::Request::get("/sse");
+            if let Some(id) = last_event_id {
+                request = request.header(LAST_EVENT_ID_HEADER, id);
+            }
+            router.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        let mut body = open(None).await.unwrap().into_body();
+        let text = read_until(&mut body, "sessionId=").await;
+        let session_id = text
+            .split("sessionId=")
+            .nth(1)
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap();
+        let uri = format!("/message?sessionId={session_id}");
+
+        for id in [1, 2] {
+            let ping = json!({ "jsonrpc": "2.0", "id": id, "method": "ping" }).to_string();
+            let response = router
+                .clone()
+                .oneshot(post(&uri, "application/json", &ping))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::ACCEPTED);
+        }
+        let text = read_until(&mut body, r#""id":2"#).await;
+        assert!(text.contains(&format!("id: {session_id}-2")));
+
+        // The client lost everything after the first event; it resumes from there.
+        drop(body);
+        let mut body = open(Some(format!("{session_id}-1")))
+            .await
+            .unwrap()
+            .into_body();
+        let text = read_until(&mut body, r#""id":2"#).await;
+        assert!(text.contains(&format!("sessionId={session_id}")));
+        assert!(!text.contains(r#""id":1"#));
+    }
+}
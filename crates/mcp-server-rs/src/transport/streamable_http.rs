@@ -0,0 +1,757 @@
+//! Streamable HTTP transport, as specified by MCP 2025-03-26.
+//!
+//! One endpoint serves the whole protocol:
+//! - `POST` carries a client message. Requests are answered with a JSON body or an SSE stream,
+//!   depending on the `Accept` header. Notifications and responses get `202 Accepted`.
+//! - `GET` opens a stream for server messages that are not tied to a request.
+//! - `DELETE` ends the session.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header},
+    response::{
+        IntoResponse, Response, Sse,
+        sse::{Event, KeepAlive},
+    },
+    routing::get,
+};
+use futures::{StreamExt, stream};
+use serde_json::Value;
+use tokio::sync::{RwLock, mpsc};
+
+use crate::{
+    core::{
+        protocol::message::JsonRpcMessage,
+        utils::{parse_json_rpc_message, queue::DEFAULT_QUEUE_CAPACITY},
+    },
+    error::{Error, Result},
+    server::Server,
+    service::context::AuthIdentity,
+    shutdown::ShutdownCoordinator,
+    transport::{
+        auth::{AuthRejection, BearerAuth},
+        http::rpc_error,
+        new_session_id,
+        traits::ServerTransport,
+    },
+};
+
+/// Header carrying the session id assigned on `initialize`.
+pub const SESSION_ID_HEADER: HeaderName = HeaderName::from_static("mcp-session-id");
+
+type ServerFactory = Arc<dyn Fn() -> Server + Send + Sync>;
+type Sessions = Arc<RwLock<HashMap<String, Session>>>;
+
+/// Why a request was turned away before reaching a session.
+type Rejection = (StatusCode, &'static str);
+
+/// Serves MCP sessions over Streamable HTTP, running one [`Server`] per session.
+#[derive(Clone)]
+pub struct StreamableHttpServer {
+    factory: ServerFactory,
+    sessions: Sessions,
+    allowed_origins: Option<Arc<[String]>>,
+    json_response: bool,
+    queue_capacity: usize,
+    auth: Option<BearerAuth>,
+    shutdown: ShutdownCoordinator,
+}
+
+impl StreamableHttpServer {
+    /// `factory` builds the server for each new session.
+    pub fn new<F>(factory: F) -> Self
+    where
+        F: Fn() -> Server + Send + Sync + 'static,
+    {
+        Self {
+            factory: Arc::new(factory),
+            sessions: Default::default(),
+            allowed_origins: None,
+            json_response: false,
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            auth: None,
+            shutdown: ShutdownCoordinator::new(),
+        }
+    }
+
+    /// Only accepts browser requests from these exact origins, e.g. `https://app.example.com`.
+    ///
+    /// By default only localhost origins are accepted, which guards local servers against DNS
+    /// rebinding. Requests without an `Origin` header are always accepted.
+    pub fn with_allowed_origins<I, S>(mut self, origins: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_origins = Some(origins.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Answers requests with a plain JSON body even when the client also accepts SSE.
+    pub fn with_json_response(mut self, json_response: bool) -> Self {
+        self.json_response = json_response;
+        self
+    }
+
+    /// Sets how many messages may wait per session and per stream before a session pushes back.
+    /// A client that sends faster than its session keeps up is answered with
+    /// `503 Service Unavailable` and `Retry-After`.
+    pub fn with_queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = capacity.max(1);
+        self
+    }
+
+    /// Requires a valid bearer token on every request. The identity of the request that opens a
+    /// session is what its handlers see; later requests must come from the same subject.
+    pub fn with_auth(mut self, auth: BearerAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Runs sessions under `shutdown`. Once it shuts down, new sessions and requests are refused
+    /// and every session drains and closes.
+    pub fn with_shutdown(mut self, shutdown: ShutdownCoordinator) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// A router serving the MCP endpoint at its root. Nest it to mount it elsewhere.
+    pub fn router(self) -> Router {
+        Router::new()
+            .route("/", get(handle_get).post(handle_post).delete(handle_delete))
+            .with_state(self)
+    }
+
+    pub async fn session_count(&self) -> usize {
+        self.sessions.read().await.len()
+    }
+
+    async fn create_session(&self, auth: Option<AuthIdentity>) -> (String, Session) {
+        let id = new_session_id();
+        let (inbound_tx, inbound_rx) = mpsc::channel(self.queue_capacity);
+        let routes = Arc::new(Mutex::new(Routes::default()));
+        let session = Session {
+            inbound: inbound_tx,
+            routes: routes.clone(),
+            auth: auth.clone(),
+        };
+        self.sessions
+            .write()
+            .await
+            .insert(id.clone(), session.clone());
+
+        let transport = HttpSessionTransport {
+            id: id.clone(),
+            inbound: inbound_rx,
+            routes,
+            auth,
+        };
+        let server = (self.factory)();
+        let sessions = self.sessions.clone();
+        let session_id = id.clone();
+        let shutdown = self.shutdown.cancelled();
+        self.shutdown.track(async move {
+            if let Err(e) = server.run_until(transport, shutdown).await {
+                tracing::error!(%session_id, error = %e, "Session ended with an error");
+            }
+            sessions.write().await.remove(&session_id);
+            tracing::info!(%session_id, "Session closed");
+        });
+
+        tracing::info!(session_id = %id, "Session created");
+        (id, session)
+    }
+
+    /// Looks up the session named by the request headers, or explains why it cannot.
+    async fn session(
+        &self,
+        headers: &HeaderMap,
+    ) -> std::result::Result<(String, Session), Response> {
+        let id = session_id(headers)
+            .ok_or_else(|| plain(StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header"))?;
+        let session = self.sessions.read().await.get(&id).cloned();
+        let Some(session) = session else {
+            return Err(plain(StatusCode::NOT_FOUND, "Session not found"));
+        };
+        self.authorize(headers, Some(&session))
+            .await
+            .map_err(IntoResponse::into_response)?;
+        Ok((id, session))
+    }
+
+    /// Checks the request's bearer token, which must belong to the subject of `session` if
+    /// given. Always succeeds without authentication configured.
+    async fn authorize(
+        &self,
+        headers: &HeaderMap,
+        session: Option<&Session>,
+    ) -> std::result::Result<Option<AuthIdentity>, AuthRejection> {
+        let Some(auth) = &self.auth else {
+            return Ok(None);
+        };
+        auth.authorize(headers, session.and_then(|s| s.auth.as_ref()))
+            .await
+            .map(Some)
+    }
+
+    fn check_origin(&self, headers: &HeaderMap) -> std::result::Result<(), Rejection> {
+        let Some(origin) = headers.get(header::ORIGIN) else {
+            return Ok(());
+        };
+        let allowed = origin
+            .to_str()
+            .is_ok_and(|origin| match &self.allowed_origins {
+                Some(allowed) => allowed.iter().any(|a| a == origin),
+                None => is_localhost_origin(origin),
+            });
+        if allowed {
+            Ok(())
+        } else {
+            tracing::warn!(?origin, "Rejected request from disallowed origin");
+            Err((StatusCode::FORBIDDEN, "Origin not allowed"))
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Session {
+    inbound: mpsc::Sender<JsonRpcMessage>,
+    routes: Arc<Mutex<Routes>>,
+    /// The caller that opened the session.
+    auth: Option<AuthIdentity>,
+}
+
+/// Decides which open HTTP stream each outgoing message is written to.
+#[derive(Default)]
+struct Routes {
+    /// The stream of the POST that carried each pending request.
+    requests: HashMap<u64, RequestStream>,
+    /// Progress tokens of pending requests, so progress goes out on the request's own stream.
+    progress: HashMap<String, u64>,
+    /// The stream opened with GET, if any.
+    standalone: Option<mpsc::Sender<JsonRpcMessage>>,
+}
+
+/// The answer to one POST.
+struct RequestStream {
+    tx: mpsc::Sender<JsonRpcMessage>,
+    /// Whether it is an SSE stream. A plain JSON answer carries only the response, so nothing
+    /// else may be sent on it.
+    sse: bool,
+}
+
+impl Routes {
+    /// The stream `msg` goes out on, if any is open.
+    fn route(&mut self, msg: &JsonRpcMessage) -> Option<mpsc::Sender<JsonRpcMessage>> {
+        match msg {
+            JsonRpcMessage::Response(response) => response.id.and_then(|id| self.complete(id)),
+            JsonRpcMessage::Error(error) => error.id.and_then(|id| self.complete(id)),
+            JsonRpcMessage::Notification(notification) => notification
+                .params
+                .as_ref()
+                .and_then(|p| p.get("progressToken"))
+                .and_then(|token| self.progress.get(&token.to_string()))
+                .and_then(|id| self.requests.get(id))
+                .map(|stream| stream.tx.clone())
+                .or_else(|| self.fallback()),
+            JsonRpcMessage::Request(_) => self.fallback(),
+            JsonRpcMessage::Nil => None,
+        }
+    }
+
+    fn complete(&mut self, id: u64) -> Option<mpsc::Sender<JsonRpcMessage>> {
+        self.progress.retain(|_, request| *request != id);
+        self.requests.remove(&id).map(|stream| stream.tx)
+    }
+
+    /// Messages not tied to a request prefer the GET stream, then any open SSE request stream.
+    fn fallback(&self) -> Option<mpsc::Sender<JsonRpcMessage>> {
+        let sse_requests = self
+            .requests
+            .values()
+            .filter(|stream| stream.sse)
+            .map(|stream| &stream.tx);
+        self.standalone
+            .iter()
+            .chain(sse_requests)
+            .find(|tx| !tx.is_closed())
+            .cloned()
+    }
+}
+
+/// The [`ServerTransport`] a session's [`Server`] runs on. Both directions are bounded, so a
+/// slow client slows the server down instead of growing memory.
+struct HttpSessionTransport {
+    id: String,
+    inbound: mpsc::Receiver<JsonRpcMessage>,
+    routes: Arc<Mutex<Routes>>,
+    auth: Option<AuthIdentity>,
+}
+
+#[async_trait]
+impl ServerTransport for HttpSessionTransport {
+    async fn read_message(&mut self) -> Option<Result<JsonRpcMessage>> {
+        self.inbound.recv().await.map(Ok)
+    }
+
+    async fn write_message(&mut self, msg: JsonRpcMessage) -> Result<()> {
+        let target = self.routes.lock().unwrap().route(&msg);
+        match target {
+            Some(tx) => {
+                if tx.send(msg).await.is_err() {
+                    tracing::debug!("Client stream closed, dropping message");
+                }
+            }
+            None => tracing::debug!("No open stream for message, dropping it"),
+        }
+        Ok(())
+    }
+
+    /// Ends every open stream, once the server has written its last message.
+    async fn close(&mut self) -> Result<()> {
+        let mut routes = self.routes.lock().unwrap();
+        routes.requests.clear();
+        routes.progress.clear();
+        routes.standalone = None;
+        Ok(())
+    }
+
+    fn session_id(&self) -> Option<String> {
+        Some(self.id.clone())
+    }
+
+    fn auth_identity(&self) -> Option<AuthIdentity> {
+        self.auth.clone()
+    }
+}
+
+async fn handle_post(
+    State(server): State<StreamableHttpServer>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Err(rejection) = server.check_origin(&headers) {
+        return rejection.into_response();
+    }
+    let accept = Accept::from_headers(&headers);
+    if !accept.json && !accept.sse {
+        return plain(
+            StatusCode::NOT_ACCEPTABLE,
+            "Accept must include application/json or text/event-stream",
+        );
+    }
+
+    let msg = match std::str::from_utf8(&body)
+        .map_err(|e| Error::InvalidMessage(e.to_string()))
+        .and_then(parse_json_rpc_message)
+    {
+        Ok(msg) => msg,
+        Err(e) => return rpc_error(StatusCode::BAD_REQUEST, &e),
+    };
+
+    if matches!(msg, JsonRpcMessage::Request(_)) && server.shutdown.is_shutting_down() {
+        return plain(StatusCode::SERVICE_UNAVAILABLE, "Server is shutting down");
+    }
+
+    let is_initialize = matches!(&msg, JsonRpcMessage::Request(r) if r.method == "initialize");
+    let (session_id, session) = if is_initialize && session_id(&headers).is_none() {
+        match server.authorize(&headers, None).await {
+            Ok(auth) => server.create_session(auth).await,
+            Err(rejection) => return rejection.into_response(),
+        }
+    } else {
+        match server.session(&headers).await {
+            Ok(found) => found,
+            Err(response) => return response,
+        }
+    };
+
+    let request = match &msg {
+        JsonRpcMessage::Request(request) => {
+            request.id.map(|id| (id, progress_token(&request.params)))
+        }
+        _ => None,
+    };
+    let Some((id, progress_token)) = request else {
+        // Notifications and responses need no answer.
+        if let Some(response) = deliver(&session, &session_id, msg) {
+            return response;
+        }
+        return with_session(StatusCode::ACCEPTED.into_response(), &session_id);
+    };
+
+    let use_sse = accept.sse && (!accept.json || !server.json_response);
+    let (tx, rx) = mpsc::channel(server.queue_capacity);
+    {
+        let mut routes = session.routes.lock().unwrap();
+        routes
+            .requests
+            .insert(id, RequestStream { tx, sse: use_sse });
+        if use_sse && let Some(token) = progress_token {
+            routes.progress.insert(token, id);
+        }
+    }
+    if let Some(response) = deliver(&session, &session_id, msg) {
+        session.routes.lock().unwrap().complete(id);
+        return response;
+    }
+
+    let response = if use_sse {
+        sse_response(rx, id)
+    } else {
+        json_response(rx, id).await
+    };
+    with_session(response, &session_id)
+}
+
+/// Hands a message to the session. Returns the answer for the client if the session cannot take
+/// it.
+fn deliver(session: &Session, session_id: &str, msg: JsonRpcMessage) -> Option<Response> {
+    match session.inbound.try_send(msg) {
+        Ok(()) => None,
+        Err(mpsc::error::TrySendError::Full(_)) => {
+            tracing::warn!(%session_id, "Session queue full, rejecting message");
+            Some(
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    [(header::RETRY_AFTER, "1")],
+                    "Session is busy",
+                )
+                    .into_response(),
+            )
+        }
+        Err(mpsc::error::TrySendError::Closed(_)) => {
+            Some(plain(StatusCode::NOT_FOUND, "Session not found"))
+        }
+    }
+}
+
+async fn handle_get(State(server): State<StreamableHttpServer>, headers: HeaderMap) -> Response {
+    if let Err(rejection) = server.check_origin(&headers) {
+        return rejection.into_response();
+    }
+    if !Accept::from_headers(&headers).sse {
+        return plain(
+            StatusCode::NOT_ACCEPTABLE,
+            "Accept must include text/event-stream",
+        );
+    }
+    let (session_id, session) = match server.session(&headers).await {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+
+    let (tx, mut rx) = mpsc::channel(server.queue_capacity);
+    session.routes.lock().unwrap().standalone = Some(tx);
+
+    let events = stream::poll_fn(move |cx| rx.poll_recv(cx)).map(|msg| event(&msg));
+    let response = Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response();
+    with_session(response, &session_id)
+}
+
+async fn handle_delete(State(server): State<StreamableHttpServer>, headers: HeaderMap) -> Response {
+    if let Err(rejection) = server.check_origin(&headers) {
+        return rejection.into_response();
+    }
+    let (session_id, _) = match server.session(&headers).await {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+
+    // Dropping the session's sender ends its transport, so the server drains and exits.
+    server.sessions.write().await.remove(&session_id);
+    tracing::info!(%session_id, "Session terminated by client");
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// Streams everything routed to the request, ending after its response.
+fn sse_response(rx: mpsc::Receiver<JsonRpcMessage>, id: u64) -> Response {
+    let events = stream::unfold(Some(rx), move |rx| async move {
+        let mut rx = rx?;
+        let msg = rx.recv().await?;
+        let done = is_response_to(&msg, id);
+        Some((event(&msg), (!done).then_some(rx)))
+    });
+    Sse::new(events).into_response()
+}
+
+/// Waits for the request's response. Anything else routed here has nowhere to go in a plain JSON
+/// reply and is dropped.
+async fn json_response(mut rx: mpsc::Receiver<JsonRpcMessage>, id: u64) -> Response {
+    while let Some(msg) = rx.recv().await {
+        if is_response_to(&msg, id) {
+            return Json(msg).into_response();
+        }
+    }
+    plain(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Session closed before responding",
+    )
+}
+
+fn event(msg: &JsonRpcMessage) -> std::result::Result<Event, axum::Error> {
+    Event::default().event("message").json_data(msg)
+}
+
+fn is_response_to(msg: &JsonRpcMessage, id: u64) -> bool {
+    match msg {
+        JsonRpcMessage::Response(response) => response.id == Some(id),
+        JsonRpcMessage::Error(error) => error.id == Some(id),
+        _ => false,
+    }
+}
+
+fn progress_token(params: &Option<Value>) -> Option<String> {
+    params
+        .as_ref()?
+        .get("_meta")?
+        .get("progressToken")
+        .map(Value::to_string)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Accept {
+    json: bool,
+    sse: bool,
+}
+
+impl Accept {
+    /// A missing `Accept` header is treated as accepting JSON only.
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let mut accept = Accept {
+            json: false,
+            sse: false,
+        };
+        let values: Vec<&str> = headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .collect();
+        if values.is_empty() {
+            accept.json = true;
+        }
+        for media_type in values.iter().flat_map(|v| v.split(',')) {
+            match media_type.split(';').next().unwrap_or_default().trim() {
+                "application/json" => accept.json = true,
+                "text/event-stream" => accept.sse = true,
+                "*/*" | "application/*" => accept.json = true,
+                _ => {}
+            }
+        }
+        accept
+    }
+}
+
+fn session_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(SESSION_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+fn is_localhost_origin(origin: &str) -> bool {
+    let host = origin.split_once("://").map_or(origin, |(_, rest)| rest);
+    ["localhost", "127.0.0.1", "[::1]"].iter().any(|local| {
+        host.strip_prefix(local)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(':'))
+    })
+}
+
+fn with_session(mut response: Response, session_id: &str) -> Response {
+    if let Ok(value) = HeaderValue::from_str(session_id) {
+        response.headers_mut().insert(SESSION_ID_HEADER, value);
+    }
+    response
+}
+
+fn plain(status: StatusCode, message: &'static str) -> Response {
+    (status, message).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{Body, to_bytes};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::service::impls::counter::CounterRouter;
+
+    fn app() -> (StreamableHttpServer, Router) {
+        let server = StreamableHttpServer::new(|| Server::new(Box::new(CounterRouter::new())));
+        (server.clone(), server.router())
+    }
+
+    fn post(session: Option<&str>, accept: &str, body: Value) -> axum::http::Request<Body> {
+        let mut request = axum::http::Request::post("/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::ACCEPT, accept);
+        if let Some(session) = session {
+            request = request.header(SESSION_ID_HEADER, session);
+        }
+        request.body(Body::from(body.to_string())).unwrap()
+    }
+
+    async fn body_text(response: Response) -> String {
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    async fn initialize(router: &Router) -> String {
+        let init = json!({
+            "jsonrpc": "2.0", "id": 1, "method": "initialize",
+            "params": { "protocolVersion": "2025-03-26", "capabilities": {},
+                        "clientInfo": { "name": "test", "version": "0" } }
+        });
+        let response = router
+            .clone()
+            .oneshot(post(None, "application/json", init))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        response.headers()[SESSION_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_session_lifecycle() {
+        let (server, router) = app();
+        let session = initialize(&router).await;
+
+        let list = json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" });
+        let response = router
+            .clone()
+            .oneshot(post(Some(&session), "application/json", list.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = serde_json::from_str(&body_text(response).await).unwrap();
+        assert_eq!(body["id"], 2);
+        assert!(body["result"]["tools"].is_array());
+
+        // The same request as an SSE stream, which ends after the response.
+        let response = router
+            .clone()
+            .oneshot(post(
+                Some(&session),
+                "application/json, text/event-stream",
+                list,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+        assert!(body_text(response).await.contains(r#""id":2"#));
+
+        let delete = axum::http::Request::delete("/")
+            .header(SESSION_ID_HEADER, &session)
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(delete).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(server.session_count().await, 0);
+
+        let ping = json!({ "jsonrpc": "2.0", "id": 3, "method": "ping" });
+        let response = router
+            .oneshot(post(Some(&session), "application/json", ping))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_bad_requests() {
+        let (_, router) = app();
+        let ping = json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" });
+
+        let response = router
+            .clone()
+            .oneshot(post(None, "application/json", ping.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let mut request = post(None, "application/json", ping);
+        request.headers_mut().insert(
+            header::ORIGIN,
+            HeaderValue::from_static("https://evil.example"),
+        );
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_notifications_skip_json_streams() {
+        let notification = || {
+            JsonRpcMessage::Notification(crate::core::protocol::message::JsonRpcNotification::new(
+                "notifications/tools/list_changed",
+                None,
+            ))
+        };
+        let mut routes = Routes::default();
+        let (json_tx, _json_rx) = mpsc::channel(1);
+        routes.requests.insert(
+            1,
+            RequestStream {
+                tx: json_tx,
+                sse: false,
+            },
+        );
+        assert!(routes.route(&notification()).is_none());
+
+        let (sse_tx, _sse_rx) = mpsc::channel(1);
+        routes.requests.insert(
+            2,
+            RequestStream {
+                tx: sse_tx.clone(),
+                sse: true,
+            },
+        );
+        let target = routes.route(&notification()).unwrap();
+        assert!(target.same_channel(&sse_tx));
+    }
+
+    #[test]
+    fn test_full_session_queue_asks_client_to_retry() {
+        let (inbound, _inbound_rx) = mpsc::channel(1);
+        let session = Session {
+            inbound,
+            routes: Default::default(),
+            auth: None,
+        };
+        let ping = || {
+            JsonRpcMessage::Request(crate::core::protocol::message::JsonRpcRequest::new(
+                Some(1),
+                "ping",
+                None,
+            ))
+        };
+
+        assert!(deliver(&session, "s1", ping()).is_none());
+        let response = deliver(&session, "s1", ping()).unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "1");
+    }
+
+    #[test]
+    fn test_localhost_origins() {
+        assert!(is_localhost_origin("http://localhost:3000"));
+        assert!(is_localhost_origin("http://127.0.0.1"));
+        assert!(is_localhost_origin("http://[::1]:8080"));
+        assert!(!is_localhost_origin("http://localhost.evil.example"));
+    }
+}
@@ -1,10 +1,16 @@
 use async_trait::async_trait;
 
-use crate::{core::protocol::message::JsonRpcMessage, error::Result};
+use crate::{
+    core::protocol::message::JsonRpcMessage, error::Result, service::context::AuthIdentity,
+};
 
 #[async_trait]
 pub trait ServerTransport: Send + Sync {
     /// Reads a JSON-RPC message (could be a Request or Notification)
+    ///
+    /// Must be cancel-safe: [`Server::run_until`](crate::server::Server::run_until) polls it in
+    /// `tokio::select!` and drops it whenever something else is ready first, so a message it has
+    /// begun to receive must not be lost when the future is dropped.
     async fn read_message(&mut self) -> Option<Result<JsonRpcMessage>>;
 
     /// Sends a JSON-RPC message (usually a Response)
@@ -14,4 +20,14 @@ pub trait ServerTransport: Send + Sync {
     async fn close(&mut self) -> Result<()> {
         Ok(())
     }
+
+    /// Identifies the session this transport serves, when the transport has such a notion.
+    fn session_id(&self) -> Option<String> {
+        None
+    }
+
+    /// The caller authenticated by the transport, if any.
+    fn auth_identity(&self) -> Option<AuthIdentity> {
+        None
+    }
 }
@@ -0,0 +1,249 @@
+//! Unix domain socket listener, for sidecar deployments.
+
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt, PermissionsExt},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use tokio::net::UnixListener;
+
+use crate::{
+    error::Result,
+    server::Server,
+    transport::{
+        ByteTransport,
+        codec::{DEFAULT_MAX_MESSAGE_SIZE, Framing},
+    },
+};
+
+/// Accepts connections on a socket path and runs a fresh [`Server`] (and so a fresh `Service`)
+/// on each, speaking newline-delimited JSON-RPC unless another [`Framing`] is chosen.
+#[derive(Clone)]
+pub struct UnixSocketServer {
+    path: PathBuf,
+    factory: Arc<dyn Fn() -> Server + Send + Sync>,
+    mode: Option<u32>,
+    remove_stale: bool,
+    framing: Framing,
+    max_message_size: usize,
+}
+
+impl UnixSocketServer {
+    pub fn new<F>(path: impl Into<PathBuf>, factory: F) -> Self
+    where
+        F: Fn() -> Server + Send + Sync + 'static,
+    {
+        Self {
+            path: path.into(),
+            factory: Arc::new(factory),
+            mode: None,
+            remove_stale: true,
+            framing: Framing::default(),
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+        }
+    }
+
+    /// Sets the permission bits of the socket file, e.g. `0o660` to restrict it to a group. The
+    /// socket only appears at its path once it has them.
+    pub fn with_permissions(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Whether a socket file left behind by a previous process may be removed before binding.
+    /// Enabled by default. A socket that still accepts connections is never removed.
+    pub fn with_stale_socket_removal(mut self, remove_stale: bool) -> Self {
+        self.remove_stale = remove_stale;
+        self
+    }
+
+    /// Sets how messages are delimited on every connection.
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Sets the largest incoming message on every connection, in bytes.
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Binds the socket, clearing a stale socket file first if allowed.
+    pub fn bind(&self) -> Result<UnixListener> {
+        if self.remove_stale {
+            remove_stale_socket(&self.path)?;
+        }
+        let listener = match self.mode {
+            Some(mode) => bind_with_mode(&self.path, mode)?,
+            None => UnixListener::bind(&self.path)?,
+        };
+        tracing::info!(path = %self.path.display(), "Listening on Unix socket");
+        Ok(listener)
+    }
+
+    /// Binds the socket and accepts connections until the listener fails. The socket file is
+    /// removed when this returns or is dropped.
+    pub async fn serve(self) -> Result<()> {
+        let listener = self.bind()?;
+        let _socket = SocketFile::new(&self.path);
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let server = (self.factory)();
+            let (framing, max_message_size) = (self.framing, self.max_message_size);
+            tokio::spawn(async move {
+                tracing::info!("Unix socket connection accepted");
+                let (reader, writer) = stream.into_split();
+                let transport = ByteTransport::new(reader, writer)
+                    .with_framing(framing)
+                    .with_max_message_size(max_message_size);
+                if let Err(e) = server.run(transport).await {
+                    tracing::warn!(error = %e, "Unix socket connection failed");
+                }
+            });
+        }
+    }
+}
+
+/// Removes the socket file it was created for when dropped, unless the path has been taken over
+/// by another file since.
+struct SocketFile {
+    path: PathBuf,
+    inode: Option<u64>,
+}
+
+impl SocketFile {
+    fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            inode: fs::symlink_metadata(path).ok().map(|m| m.ino()),
+        }
+    }
+}
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        let current = fs::symlink_metadata(&self.path).ok().map(|m| m.ino());
+        if self.inode.is_some() && current == self.inode {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Binds inside a directory only the owner can enter, sets `mode` there and then links the socket
+/// into place, so nobody can connect while it still has the default permissions.
+fn bind_with_mode(path: &Path, mode: u32) -> io::Result<UnixListener> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    // A random name, so that a directory left behind by a crash never blocks a later bind.
+    let staging = parent.join(format!(".{name}.{:016x}", rand::random::<u64>()));
+    fs::DirBuilder::new().mode(0o700).create(&staging)?;
+    let staged = staging.join("s");
+
+    let bind = || {
+        let listener = UnixListener::bind(&staged)?;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(mode))?;
+        // Unlike a rename, a link never replaces a socket that appeared in the meantime.
+        fs::hard_link(&staged, path)?;
+        Ok(listener)
+    };
+    let result = bind();
+    let _ = fs::remove_file(&staged);
+    let _ = fs::remove_dir(&staging);
+    result
+}
+
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{} exists and is not a socket", path.display()),
+        ));
+    }
+    if std::os::unix::net::UnixStream::connect(path).is_ok() {
+        return Err(io::Error::new(
+            ErrorKind::AddrInUse,
+            format!("another server is listening on {}", path.display()),
+        ));
+    }
+
+    tracing::info!(path = %path.display(), "Removing stale socket file");
+    fs::remove_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::UnixStream,
+    };
+
+    use super::*;
+    use crate::service::impls::counter::CounterRouter;
+
+    #[tokio::test]
+    async fn test_serves_over_socket_after_removing_stale_file() {
+        let path = std::env::temp_dir().join(format!("mcp-{:x}.sock", rand::random::<u64>()));
+        // Leave a socket file behind with nobody listening on it.
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+        let server = UnixSocketServer::new(&path, || Server::new(Box::new(CounterRouter::new())))
+            .with_permissions(0o600);
+        let listener_server = server.clone();
+        let serving = tokio::spawn(listener_server.serve());
+
+        let mut stream = loop {
+            match UnixStream::connect(&path).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::task::yield_now().await,
+            }
+        };
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let staging = format!(".{}.", path.file_name().unwrap().to_string_lossy());
+        let leftovers = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().starts_with(&staging)
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+
+        let ping = json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" });
+        stream
+            .write_all(format!("{ping}\n").as_bytes())
+            .await
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(&mut stream)
+            .read_line(&mut line)
+            .await
+            .unwrap();
+        let response: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(response["id"], 1);
+
+        // A live socket must not be taken over.
+        assert!(server.bind().is_err());
+
+        serving.abort();
+        let _ = serving.await;
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,219 @@
+//! WebSocket transport: one JSON-RPC message per text frame.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::{SinkExt, StreamExt};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpListener,
+};
+use tokio_tungstenite::{
+    WebSocketStream,
+    tungstenite::{
+        self, Message,
+        error::CapacityError,
+        protocol::{CloseFrame, WebSocketConfig, frame::coding::CloseCode},
+    },
+};
+
+use crate::{
+    core::{protocol::message::JsonRpcMessage, utils::parse_json_rpc_message},
+    error::{Error, Result},
+    server::Server,
+    transport::traits::ServerTransport,
+};
+
+/// Largest frame, and message, accepted when nothing else is configured.
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 4 * 1024 * 1024;
+
+/// A [`ServerTransport`] over an established WebSocket connection.
+///
+/// Pings are answered with pongs automatically. A close frame from the client, or a frame over
+/// the size limit, ends the transport.
+pub struct WebSocketTransport<S> {
+    ws: WebSocketStream<S>,
+}
+
+impl<S> WebSocketTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    pub fn new(ws: WebSocketStream<S>) -> Self {
+        Self { ws }
+    }
+
+    /// Performs the server side of the WebSocket handshake on an accepted stream.
+    pub async fn accept(stream: S, max_frame_size: usize) -> Result<Self> {
+        let config = WebSocketConfig::default()
+            .max_frame_size(Some(max_frame_size))
+            .max_message_size(Some(max_frame_size));
+        let ws = tokio_tungstenite::accept_async_with_config(stream, Some(config))
+            .await
+            .map_err(ws_error)?;
+        Ok(Self::new(ws))
+    }
+}
+
+#[async_trait]
+impl<S> ServerTransport for WebSocketTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + Sync,
+{
+    async fn read_message(&mut self) -> Option<Result<JsonRpcMessage>> {
+        loop {
+            match self.ws.next().await? {
+                Ok(Message::Text(text)) => return Some(parse_json_rpc_message(text.as_str())),
+                Ok(Message::Binary(_)) => {
+                    return Some(Err(Error::InvalidMessage(
+                        "Binary frames are not supported".into(),
+                    )));
+                }
+                // tungstenite queues the pong itself and sends it on the next read or write.
+                Ok(Message::Ping(_) | Message::Pong(_) | Message::Frame(_)) => continue,
+                Ok(Message::Close(frame)) => {
+                    tracing::info!(?frame, "Client closed WebSocket");
+                    return None;
+                }
+                Err(tungstenite::Error::Capacity(e)) => {
+                    tracing::warn!(error = %e, "WebSocket frame too large, closing connection");
+                    let reason = match e {
+                        CapacityError::MessageTooLong { .. } => "Message too large",
+                        _ => "Frame too large",
+                    };
+                    let _ = self
+                        .ws
+                        .close(Some(CloseFrame {
+                            code: CloseCode::Size,
+                            reason: reason.into(),
+                        }))
+                        .await;
+                    return None;
+                }
+                Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                    return None;
+                }
+                Err(e) => {
+                    // Anything else leaves the stream in an unknown state, so stop reading.
+                    tracing::warn!(error = %e, "WebSocket read failed");
+                    return None;
+                }
+            }
+        }
+    }
+
+    async fn write_message(&mut self, msg: JsonRpcMessage) -> Result<()> {
+        let json = serde_json::to_string(&msg)?;
+        self.ws.send(Message::text(json)).await.map_err(ws_error)
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        match self.ws.close(None).await {
+            Ok(())
+            | Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                Ok(())
+            }
+            Err(e) => Err(ws_error(e)),
+        }
+    }
+}
+
+fn ws_error(e: tungstenite::Error) -> Error {
+    match e {
+        tungstenite::Error::Io(e) => Error::Io(e),
+        e => Error::System(format!("WebSocket error: {e}")),
+    }
+}
+
+/// Accepts WebSocket connections and runs a fresh [`Server`] on each.
+#[derive(Clone)]
+pub struct WebSocketServer {
+    factory: Arc<dyn Fn() -> Server + Send + Sync>,
+    max_frame_size: usize,
+}
+
+impl WebSocketServer {
+    /// `factory` builds the server for each new connection.
+    pub fn new<F>(factory: F) -> Self
+    where
+        F: Fn() -> Server + Send + Sync + 'static,
+    {
+        Self {
+            factory: Arc::new(factory),
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+
+    pub fn with_max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    /// Accepts connections until the listener fails.
+    pub async fn serve(self, listener: TcpListener) -> Result<()> {
+        loop {
+            let (stream, addr) = listener.accept().await?;
+            let server = self.clone();
+            tokio::spawn(async move {
+                tracing::info!(%addr, "WebSocket connection accepted");
+                if let Err(e) = server.serve_connection(stream).await {
+                    tracing::warn!(%addr, error = %e, "WebSocket connection failed");
+                }
+            });
+        }
+    }
+
+    /// Completes the handshake on one accepted stream and serves it until it closes.
+    pub async fn serve_connection<S>(&self, stream: S) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + Sync,
+    {
+        let transport = WebSocketTransport::accept(stream, self.max_frame_size).await?;
+        (self.factory)().run(transport).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::service::impls::counter::CounterRouter;
+
+    #[tokio::test]
+    async fn test_websocket_round_trip_and_frame_limit() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = WebSocketServer::new(|| Server::new(Box::new(CounterRouter::new())))
+            .with_max_frame_size(1024);
+        tokio::spawn(server.serve(listener));
+
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{addr}"))
+            .await
+            .unwrap();
+
+        let ping = json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" });
+        client.send(Message::text(ping.to_string())).await.unwrap();
+        client.send(Message::Ping(vec![1].into())).await.unwrap();
+
+        let mut got_response = false;
+        let mut got_pong = false;
+        while !(got_response && got_pong) {
+            match client.next().await.unwrap().unwrap() {
+                Message::Text(text) => {
+                    let response: Value = serde_json::from_str(text.as_str()).unwrap();
+                    assert_eq!(response["id"], 1);
+                    got_response = true;
+                }
+                Message::Pong(_) => got_pong = true,
+                other => panic!("Unexpected frame {other:?}"),
+            }
+        }
+
+        client.send(Message::text("x".repeat(2048))).await.unwrap();
+        match client.next().await.unwrap().unwrap() {
+            Message::Close(Some(frame)) => assert_eq!(frame.code, CloseCode::Size),
+            other => panic!("Expected close frame, got {other:?}"),
+        }
+    }
+}
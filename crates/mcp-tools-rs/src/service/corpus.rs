@@ -9,9 +9,9 @@ use serde_json::Value;
 use service_utils_rs::utils::request::Request;
 
 use crate::{
-    core::{Resource, Tool, content::Content, protocol::capabilities::ServerCapabilities},
+    core::{Resource, Tool, content::Content},
     error::{Error, Result},
-    server::service::{capabilities::CapabilitiesBuilder, traits::Service},
+    server::service::{context::RequestContext, traits::Service},
 };
 
 /// Service for expanding corpus text via an LLM (e.g., OpenAI Chat API).
@@ -86,14 +86,6 @@ where
             .into()
     }
 
-    fn capabilities(&self) -> ServerCapabilities {
-        CapabilitiesBuilder::new()
-            .with_tools(false)
-            .with_resources(false, false)
-            .with_prompts(false)
-            .build()
-    }
-
     fn list_tools(&self) -> Vec<Tool> {
         vec![Tool::new(
             "expand_corpus".to_string(),
@@ -110,7 +102,12 @@ where
         )]
     }
 
-    async fn call_tool(&self, tool_name: &str, args: Value) -> Result<Vec<Content>> {
+    async fn call_tool(
+        &self,
+        tool_name: &str,
+        args: Value,
+        _ctx: RequestContext,
+    ) -> Result<Vec<Content>> {
         match tool_name {
             "expand_corpus" => {
                 let content_path = args
@@ -134,7 +131,7 @@ where
         Vec::new()
     }
 
-    async fn read_resource(&self, _uri: &str) -> Result<String> {
+    async fn read_resource(&self, _uri: &str, _ctx: RequestContext) -> Result<String> {
         Err(Error::System("No resources available".into()))
     }
 }
@@ -1 +1,2 @@
-pub mod jsonrpc_frame_codec;
+/// The JSON-RPC framing shared by the byte-stream transports.
+pub use mcp_server_rs::transport::codec;